@@ -0,0 +1,312 @@
+//! Macros for [lemon-graph](https://github.com/kayhhh/lemon/tree/main/crates/lemon-graph).
+//!
+//! Also available from `lemon_graph` with the `macros` feature.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    parse_macro_input, Expr, Ident, LitInt, Token,
+};
+
+/// Builds a graph using a [GraphBuilder](https://docs.rs/lemon-graph/latest/lemon_graph/struct.GraphBuilder.html),
+/// returning the result of `build()`.
+///
+/// Each statement ends with a `;`:
+///
+/// - `use registry;` uses the given registry, instead of the core nodes.
+/// - `name = |graph| ...;` adds a node, using a function to create it.
+/// - `name: data;` adds a node from the registry, given its `NodeData`.
+/// - `a -> b -> c;` runs each node after the one before it.
+/// - `a.1 -> b;` runs `b` after the execution output of `a` at the given index.
+/// - `a !> b;` runs `b` if `a` fails, instead of stopping execution.
+/// - `a.port => b.port;` connects an output port to an input port.
+/// - `a.port = value;` sets the value of an input port.
+///
+/// Ports are either names or data indices.
+///
+/// ```
+/// use lemon_graph::{nodes::CallbackNode, Executor, NodeData, Value};
+/// use lemon_graph_macros::graph;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let mut built = graph! {
+///         greet = |graph| CallbackNode::new(graph, |value| {
+///             Value::String(format!("Hello, {}", value))
+///         });
+///         log: NodeData::new("lemon.log");
+///
+///         greet -> log;
+///         greet.output => log.message;
+///         greet.input = "world".to_string();
+///     }
+///     .unwrap();
+///
+///     let greet = built["greet"];
+///     Executor::execute(&mut built.graph, greet).await.unwrap();
+/// }
+/// ```
+#[proc_macro]
+pub fn graph(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as GraphInput);
+    input.expand().into()
+}
+
+struct GraphInput {
+    registry: Option<Expr>,
+    statements: Vec<Statement>,
+}
+
+enum Statement {
+    Add(Ident, Expr),
+    Create(Ident, Expr),
+    Chain(Vec<Ident>),
+    /// Chain starting from an execution output of the first node.
+    Branch(Ident, usize, Vec<Ident>),
+    OnError(Ident, Ident),
+    Connect(Port, Port),
+    Set(Port, Expr),
+}
+
+/// `node.port`, where the port is a name or index.
+struct Port {
+    node: Ident,
+    port: String,
+}
+
+impl Port {
+    fn parse_after(node: Ident, input: ParseStream) -> syn::Result<Self> {
+        input.parse::<Token![.]>()?;
+
+        let port = if input.peek(LitInt) {
+            input.parse::<LitInt>()?.base10_digits().to_string()
+        } else {
+            input.parse::<Ident>()?.to_string()
+        };
+
+        Ok(Self { node, port })
+    }
+
+    fn path(&self) -> String {
+        format!("{}.{}", self.node, self.port)
+    }
+}
+
+impl Parse for GraphInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut registry = None;
+        let mut statements = Vec::new();
+
+        while !input.is_empty() {
+            if input.peek(Token![use]) {
+                let token = input.parse::<Token![use]>()?;
+
+                if registry.is_some() {
+                    return Err(syn::Error::new(token.span, "Registry is already set"));
+                }
+
+                registry = Some(input.parse()?);
+            } else {
+                statements.push(input.parse()?);
+            }
+
+            input.parse::<Token![;]>()?;
+        }
+
+        Ok(Self {
+            registry,
+            statements,
+        })
+    }
+}
+
+impl Parse for Statement {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let node = input.parse::<Ident>()?;
+
+        if input.peek(Token![=]) && !input.peek(Token![=>]) {
+            input.parse::<Token![=]>()?;
+            return Ok(Self::Add(node, input.parse()?));
+        }
+
+        if input.peek(Token![:]) {
+            input.parse::<Token![:]>()?;
+            return Ok(Self::Create(node, input.parse()?));
+        }
+
+        if input.peek(Token![->]) {
+            return Ok(Self::Chain(parse_chain(vec![node], input)?));
+        }
+
+        if input.peek(Token![!]) && input.peek2(Token![>]) {
+            input.parse::<Token![!]>()?;
+            input.parse::<Token![>]>()?;
+            return Ok(Self::OnError(node, input.parse()?));
+        }
+
+        if input.peek(Token![.]) {
+            let port = Port::parse_after(node, input)?;
+
+            if input.peek(Token![->]) {
+                let output = port
+                    .port
+                    .parse()
+                    .map_err(|_| input.error("Execution outputs are indices"))?;
+                return Ok(Self::Branch(
+                    port.node,
+                    output,
+                    parse_chain(Vec::new(), input)?,
+                ));
+            }
+
+            if input.peek(Token![=>]) {
+                input.parse::<Token![=>]>()?;
+                let node = input.parse()?;
+                return Ok(Self::Connect(port, Port::parse_after(node, input)?));
+            }
+
+            input.parse::<Token![=]>()?;
+            return Ok(Self::Set(port, input.parse()?));
+        }
+
+        Err(input.error("Expected `=`, `:`, `->`, `!>` or `.` after node name"))
+    }
+}
+
+/// Parses `-> b -> c`, adding each node to the given ones.
+fn parse_chain(mut nodes: Vec<Ident>, input: ParseStream) -> syn::Result<Vec<Ident>> {
+    while input.peek(Token![->]) {
+        input.parse::<Token![->]>()?;
+        nodes.push(input.parse()?);
+    }
+
+    Ok(nodes)
+}
+
+impl GraphInput {
+    fn expand(&self) -> TokenStream2 {
+        let builder = match &self.registry {
+            Some(registry) => quote! { ::lemon_graph::GraphBuilder::with_registry(#registry) },
+            None => quote! { ::lemon_graph::GraphBuilder::new() },
+        };
+
+        let calls = self.statements.iter().map(|statement| match statement {
+            Statement::Add(node, create) => {
+                let node = node.to_string();
+                quote! { .add(#node, #create) }
+            }
+            Statement::Create(node, data) => {
+                let node = node.to_string();
+                quote! { .create(#node, #data) }
+            }
+            Statement::Chain(nodes) => {
+                let nodes = nodes.iter().map(Ident::to_string);
+                quote! { .chain([#(#nodes),*]) }
+            }
+            Statement::Branch(from, output, nodes) => {
+                let from = from.to_string();
+                let nodes = nodes.iter().map(Ident::to_string).collect::<Vec<_>>();
+                let to = &nodes[0];
+
+                if nodes.len() > 1 {
+                    quote! { .then_output(#from, #output, #to).chain([#(#nodes),*]) }
+                } else {
+                    quote! { .then_output(#from, #output, #to) }
+                }
+            }
+            Statement::OnError(from, to) => {
+                let (from, to) = (from.to_string(), to.to_string());
+                quote! { .on_error(#from, #to) }
+            }
+            Statement::Connect(from, to) => {
+                let (from, to) = (from.path(), to.path());
+                quote! { .connect(#from, #to) }
+            }
+            Statement::Set(port, value) => {
+                let port = port.path();
+                quote! { .set(#port, ::core::convert::Into::into(#value)) }
+            }
+        });
+
+        quote! { #builder #(#calls)* .build() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error(input: &str) -> String {
+        match syn::parse_str::<GraphInput>(input) {
+            Ok(_) => panic!("Expected {:?} to fail to parse", input),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_expand() {
+        let input: GraphInput = syn::parse_str(
+            "
+            use registry;
+            a = |graph| make(graph);
+            b: data;
+            a -> b -> c;
+            a.0 => b.1;
+            c.input = 1;
+            b.1 -> c;
+            c.2 -> a -> b;
+            a !> c;
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(input.statements.len(), 8);
+        assert!(matches!(&input.statements[2], Statement::Chain(nodes) if nodes.len() == 3));
+
+        let expected = quote! {
+            ::lemon_graph::GraphBuilder::with_registry(registry)
+                .add("a", |graph| make(graph))
+                .create("b", data)
+                .chain(["a", "b", "c"])
+                .connect("a.0", "b.1")
+                .set("c.input", ::core::convert::Into::into(1))
+                .then_output("b", 1usize, "c")
+                .then_output("c", 2usize, "a")
+                .chain(["a", "b"])
+                .on_error("a", "c")
+                .build()
+        };
+        assert_eq!(input.expand().to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn test_default_registry() {
+        let input: GraphInput = syn::parse_str("a -> b;").unwrap();
+
+        let expected = quote! {
+            ::lemon_graph::GraphBuilder::new().chain(["a", "b"]).build()
+        };
+        assert_eq!(input.expand().to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse_error("use registry; use other;"),
+            "Registry is already set"
+        );
+        assert_eq!(
+            parse_error("a + b;"),
+            "Expected `=`, `:`, `->`, `!>` or `.` after node name"
+        );
+        assert_eq!(parse_error("a.out -> b;"), "Execution outputs are indices");
+        assert_eq!(
+            parse_error("a ! b;"),
+            "Expected `=`, `:`, `->`, `!>` or `.` after node name"
+        );
+        assert_eq!(parse_error("a.b;"), "expected `=`");
+        assert_eq!(parse_error("a -> b"), "expected `;`");
+    }
+}
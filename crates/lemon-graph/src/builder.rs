@@ -0,0 +1,363 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    ops::Index,
+};
+
+use petgraph::graph::NodeIndex;
+use thiserror::Error;
+
+use crate::{
+    nodes::{ConnectError, Node, Store},
+    Graph, LoadError, NodeData, NodeRegistry, Value,
+};
+
+/// Problem found when building a graph with a [GraphBuilder].
+#[derive(Debug, Error)]
+pub enum BuildError {
+    #[error("Node {0} is defined more than once")]
+    DuplicateNode(String),
+    #[error("Unknown node {0}")]
+    UnknownNode(String),
+    #[error("Failed to create node {node}: {source}")]
+    Load { node: String, source: LoadError },
+    #[error("Invalid port {0}, expected `node.port`")]
+    InvalidPort(String),
+    #[error("Node {node} has no port {port}")]
+    UnknownPort { node: String, port: String },
+    #[error("Cannot connect {from} to {to}: {source}")]
+    Connect {
+        from: String,
+        to: String,
+        source: ConnectError,
+    },
+}
+
+enum Wire {
+    Then {
+        from: String,
+        output: usize,
+        to: String,
+    },
+    OnError {
+        from: String,
+        to: String,
+    },
+    Connect {
+        from: String,
+        to: String,
+    },
+    Set {
+        port: String,
+        value: Value,
+    },
+}
+
+/// Builds a [Graph] by name, instead of by index.
+///
+/// Nodes are added under a name, and ports are referred to as `node.port`.
+/// The port is either a name declared by the node, or its data index.
+/// Wiring is applied at [build](GraphBuilder::build), which reports every
+/// problem found rather than stopping at the first.
+///
+/// ```
+/// use lemon_graph::{nodes::CallbackNode, GraphBuilder, NodeData, Value};
+///
+/// let built = GraphBuilder::new()
+///     .add("greet", |graph| {
+///         CallbackNode::new(graph, |value| Value::String(format!("Hello, {}", value)))
+///     })
+///     .create("log", NodeData::new("lemon.log"))
+///     .then("greet", "log")
+///     .connect("greet.output", "log.message")
+///     .set("greet.input", Value::String("world".to_string()))
+///     .build()
+///     .unwrap();
+///
+/// let greet = built["greet"];
+/// ```
+pub struct GraphBuilder {
+    graph: Graph,
+    registry: NodeRegistry,
+    nodes: HashMap<String, NodeIndex>,
+    wires: Vec<Wire>,
+    errors: Vec<BuildError>,
+}
+
+impl Default for GraphBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GraphBuilder {
+    /// Creates a builder using a registry of the core nodes.
+    pub fn new() -> Self {
+        Self::with_registry(NodeRegistry::new())
+    }
+
+    /// Creates a builder using the given registry to create nodes.
+    pub fn with_registry(registry: NodeRegistry) -> Self {
+        Self {
+            graph: Graph::default(),
+            registry,
+            nodes: HashMap::new(),
+            wires: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Adds a node, using the given function to create it.
+    pub fn add<N: Into<NodeIndex>>(
+        mut self,
+        name: impl Into<String>,
+        create: impl FnOnce(&mut Graph) -> N,
+    ) -> Self {
+        let index = create(&mut self.graph).into();
+        self.insert(name.into(), index);
+        self
+    }
+
+    /// Adds a node from the registry.
+    pub fn create(mut self, name: impl Into<String>, data: NodeData) -> Self {
+        let name = name.into();
+
+        match self.registry.create(&mut self.graph, &data) {
+            Ok(index) => self.insert(name, index),
+            Err(source) => self.errors.push(BuildError::Load { node: name, source }),
+        }
+
+        self
+    }
+
+    fn insert(&mut self, name: String, index: NodeIndex) {
+        match self.nodes.entry(name) {
+            Entry::Occupied(entry) => {
+                let name = entry.key().clone();
+                self.errors.push(BuildError::DuplicateNode(name));
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(index);
+            }
+        }
+    }
+
+    /// Runs `to` after `from`.
+    pub fn then(self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.then_output(from, 0, to)
+    }
+
+    /// Runs `to` after the given execution output of `from`.
+    pub fn then_output(
+        mut self,
+        from: impl Into<String>,
+        output: usize,
+        to: impl Into<String>,
+    ) -> Self {
+        self.wires.push(Wire::Then {
+            from: from.into(),
+            output,
+            to: to.into(),
+        });
+        self
+    }
+
+    /// Runs each node after the one before it.
+    pub fn chain<S: Into<String>>(mut self, nodes: impl IntoIterator<Item = S>) -> Self {
+        let nodes = nodes.into_iter().map(Into::into).collect::<Vec<String>>();
+
+        for pair in nodes.windows(2) {
+            self = self.then(pair[0].clone(), pair[1].clone());
+        }
+
+        self
+    }
+
+    /// Runs `to` if `from` fails, instead of stopping execution.
+    pub fn on_error(mut self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.wires.push(Wire::OnError {
+            from: from.into(),
+            to: to.into(),
+        });
+        self
+    }
+
+    /// Sets the input port `to` to read from the output port `from`.
+    pub fn connect(mut self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.wires.push(Wire::Connect {
+            from: from.into(),
+            to: to.into(),
+        });
+        self
+    }
+
+    /// Sets the value of an input port.
+    pub fn set(mut self, port: impl Into<String>, value: Value) -> Self {
+        self.wires.push(Wire::Set {
+            port: port.into(),
+            value,
+        });
+        self
+    }
+
+    /// Applies the wiring, returning every problem found.
+    pub fn build(mut self) -> Result<BuiltGraph, Vec<BuildError>> {
+        for wire in std::mem::take(&mut self.wires) {
+            if let Err(e) = self.apply(wire) {
+                self.errors.push(e);
+            }
+        }
+
+        if !self.errors.is_empty() {
+            return Err(self.errors);
+        }
+
+        Ok(BuiltGraph {
+            graph: self.graph,
+            nodes: self.nodes,
+        })
+    }
+
+    fn apply(&mut self, wire: Wire) -> Result<(), BuildError> {
+        match wire {
+            Wire::Then { from, output, to } => {
+                let (from, to) = (self.node(&from)?, self.node(&to)?);
+                from.run_before_output(&mut self.graph, to, output);
+            }
+            Wire::OnError { from, to } => {
+                let (from, to) = (self.node(&from)?, self.node(&to)?);
+                from.on_error(&mut self.graph, to);
+            }
+            Wire::Connect { from, to } => {
+                let output = self.port(&from, false)?;
+                let input = self.port(&to, true)?;
+                input
+                    .set_input(&mut self.graph, Some(output))
+                    .map_err(|source| BuildError::Connect { from, to, source })?;
+            }
+            Wire::Set { port, value } => {
+                let store = self.port(&port, true)?;
+                store.set_value(&mut self.graph, value);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn node(&self, name: &str) -> Result<NodeIndex, BuildError> {
+        self.nodes
+            .get(name)
+            .copied()
+            .ok_or_else(|| BuildError::UnknownNode(name.to_string()))
+    }
+
+    /// Resolves a `node.port` to its store.
+    fn port(&self, port: &str, input: bool) -> Result<Store, BuildError> {
+        let (node_name, port_name) = port
+            .split_once('.')
+            .ok_or_else(|| BuildError::InvalidPort(port.to_string()))?;
+        let node = self.node(node_name)?;
+
+        let unknown = || BuildError::UnknownPort {
+            node: node_name.to_string(),
+            port: port_name.to_string(),
+        };
+
+        let index = match port_name.parse::<usize>() {
+            Ok(index) => index,
+            Err(_) if input => self.graph[node]
+                .input_index(port_name)
+                .ok_or_else(unknown)?,
+            Err(_) => self.graph[node]
+                .output_index(port_name)
+                .ok_or_else(unknown)?,
+        };
+
+        if input {
+            node.input_store(&self.graph, index)
+        } else {
+            node.output_store(&self.graph, index)
+        }
+        .map_err(|_| unknown())
+    }
+}
+
+/// Graph created by a [GraphBuilder], along with the index of each named node.
+pub struct BuiltGraph {
+    pub graph: Graph,
+    pub nodes: HashMap<String, NodeIndex>,
+}
+
+impl Index<&str> for BuiltGraph {
+    type Output = NodeIndex;
+
+    /// Returns the index of the named node.
+    /// Panics if there is no such node.
+    fn index(&self, name: &str) -> &NodeIndex {
+        &self.nodes[name]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{nodes::CallbackNode, Executor, GraphNode};
+
+    use super::*;
+
+    fn double(graph: &mut Graph) -> CallbackNode {
+        CallbackNode::new(graph, |value| match value {
+            Value::USize(value) => Value::USize(value * 2),
+            value => value,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_builder() {
+        let mut built = GraphBuilder::new()
+            .add("a", double)
+            .add("b", double)
+            .add("c", double)
+            .create("log", NodeData::new("lemon.log"))
+            .chain(["a", "b", "c", "log"])
+            .connect("a.output", "b.input")
+            .connect("b.0", "c.0")
+            .connect("c.0", "log.message")
+            .set("a.0", Value::USize(1))
+            .build()
+            .unwrap();
+
+        let a = built["a"];
+        Executor::execute(&mut built.graph, a).await.unwrap();
+
+        let output = built["c"].output_store(&built.graph, 0).unwrap();
+        assert!(matches!(
+            built.graph[output.0],
+            GraphNode::Store(Value::USize(8))
+        ));
+    }
+
+    #[test]
+    fn test_build_errors() {
+        let errors = GraphBuilder::new()
+            .add("a", double)
+            .add("a", double)
+            .create("prompt", NodeData::new("lemon.prompt"))
+            .create("missing", NodeData::new("test.missing"))
+            .then("a", "b")
+            .connect("a", "prompt.input")
+            .connect("a.0", "prompt.message")
+            .build()
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            errors.as_slice(),
+            [
+                BuildError::DuplicateNode(_),
+                BuildError::Load { .. },
+                BuildError::UnknownNode(b),
+                BuildError::InvalidPort(_),
+                BuildError::UnknownPort { port, .. },
+            ] if b == "b" && port == "message"
+        ));
+    }
+}
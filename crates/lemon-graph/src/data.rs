@@ -0,0 +1,229 @@
+use petgraph::{graph::NodeIndex, visit::EdgeRef};
+use thiserror::Error;
+
+use crate::{Graph, GraphEdge, GraphNode, Value};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Type name and configuration of a node, used to save and load it.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NodeData {
+    /// Name of the node type, such as `lemon.log`.
+    pub name: String,
+    /// Configuration used to recreate the node.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub config: Option<Value>,
+}
+
+impl NodeData {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            config: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SavedNode {
+    /// Executable node, recreated from its data when loading.
+    Node(NodeData),
+    /// Store with its current value.
+    Store(Value),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SavedEdge {
+    pub source: usize,
+    pub target: usize,
+    pub weight: GraphEdge,
+}
+
+#[derive(Debug, Error)]
+pub enum SaveError {
+    #[error("Node {0:?} cannot be saved")]
+    Unsupported(NodeIndex),
+}
+
+#[derive(Debug, Error)]
+pub enum LoadError {
+    #[error("Unknown node type: {0}")]
+    UnknownNode(String),
+    #[error("Invalid config for {0}")]
+    InvalidConfig(String),
+    #[error("Edge references missing node {0}")]
+    MissingNode(usize),
+}
+
+/// Serializable representation of a [Graph].
+///
+/// Node indices are preserved, so indices from the saved graph
+/// remain valid in the loaded one.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GraphData {
+    pub nodes: Vec<SavedNode>,
+    pub edges: Vec<SavedEdge>,
+}
+
+impl GraphData {
+    /// Saves a graph.
+    /// Fails if any node does not provide its [NodeData].
+    pub fn save(graph: &Graph) -> Result<Self, SaveError> {
+        let nodes = graph
+            .node_indices()
+            .map(|idx| {
+                match &graph[idx] {
+                    GraphNode::Store(value) => Some(SavedNode::Store(value.clone())),
+                    node => node.data().map(SavedNode::Node),
+                }
+                .ok_or(SaveError::Unsupported(idx))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let edges = graph
+            .edge_references()
+            .map(|edge| SavedEdge {
+                source: edge.source().index(),
+                target: edge.target().index(),
+                weight: *edge.weight(),
+            })
+            .collect();
+
+        Ok(Self { nodes, edges })
+    }
+
+    /// Loads the graph, using the provided function to recreate executable nodes.
+    pub fn load(
+        self,
+        load_node: impl Fn(&NodeData) -> Result<GraphNode, LoadError>,
+    ) -> Result<Graph, LoadError> {
+        let mut graph = Graph::with_capacity(self.nodes.len(), self.edges.len());
+
+        for node in self.nodes {
+            let weight = match node {
+                SavedNode::Node(data) => load_node(&data)?,
+                SavedNode::Store(value) => GraphNode::Store(value),
+            };
+
+            graph.add_node(weight);
+        }
+
+        for edge in self.edges {
+            for idx in [edge.source, edge.target] {
+                if idx >= graph.node_count() {
+                    return Err(LoadError::MissingNode(idx));
+                }
+            }
+
+            graph.add_edge(
+                NodeIndex::new(edge.source),
+                NodeIndex::new(edge.target),
+                edge.weight,
+            );
+        }
+
+        Ok(graph)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_test::traced_test;
+
+    use crate::{
+        nodes::{CallbackNode, JoinNode, LogNode, Node},
+        Executor, NodeRegistry,
+    };
+
+    use super::*;
+
+    /// start -> join -> log, with the join passing the message to the log.
+    fn log_graph() -> (Graph, LogNode) {
+        let mut graph = Graph::default();
+        let start = LogNode::new(&mut graph);
+        let message = start.message(&graph).unwrap();
+        message.set_value(&mut graph, "Starting".to_string().into());
+
+        let join = JoinNode::new(&mut graph, 1);
+        join.run_after(&mut graph, start.0);
+        let input = join.input(&graph, 0).unwrap();
+        input.set_value(&mut graph, "Hello, world!".to_string().into());
+
+        let log = LogNode::new(&mut graph);
+        log.run_after(&mut graph, join.0);
+        let output = join.output(&graph, 0).unwrap();
+        let message = log.message(&graph).unwrap();
+        message.set_input(&mut graph, Some(output)).unwrap();
+
+        (graph, start)
+    }
+
+    #[test]
+    fn test_save_unsupported() {
+        let mut graph = Graph::default();
+        let callback = CallbackNode::new(&mut graph, |value| value);
+
+        let res = GraphData::save(&graph);
+        assert!(matches!(res, Err(SaveError::Unsupported(idx)) if idx == callback.0));
+    }
+
+    #[test]
+    fn test_load_unknown() {
+        let data = GraphData {
+            nodes: vec![SavedNode::Node(NodeData::new("unknown"))],
+            edges: Vec::new(),
+        };
+
+        let registry = NodeRegistry::new();
+        let res = data.load(|data| registry.load(data));
+        assert!(matches!(res, Err(LoadError::UnknownNode(name)) if name == "unknown"));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_save_load() {
+        let (graph, log) = log_graph();
+
+        let data = GraphData::save(&graph).unwrap();
+        let registry = NodeRegistry::new();
+        let mut loaded = data.clone().load(|data| registry.load(data)).unwrap();
+
+        assert_eq!(GraphData::save(&loaded).unwrap(), data);
+
+        Executor::execute(&mut loaded, log.0).await.unwrap();
+
+        assert!(logs_contain("Hello, world!"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json() {
+        let (graph, _) = log_graph();
+        let data = GraphData::save(&graph).unwrap();
+
+        let json = serde_json::to_string(&data).unwrap();
+        let loaded = serde_json::from_str::<GraphData>(&json).unwrap();
+
+        assert_eq!(loaded, data);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_ron() {
+        let (graph, _) = log_graph();
+        let data = GraphData::save(&graph).unwrap();
+
+        let ron = ron::to_string(&data).unwrap();
+        let loaded = ron::from_str::<GraphData>(&ron).unwrap();
+
+        assert_eq!(loaded, data);
+    }
+}
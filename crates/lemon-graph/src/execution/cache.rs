@@ -0,0 +1,300 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use crate::Value;
+
+/// Stores the outputs of pure nodes, keyed by a hash of the node and its inputs.
+/// See [ExecutionStep::cache_key](crate::ExecutionStep::cache_key).
+pub trait OutputCache: Send + Sync {
+    /// Returns the cached outputs for the key, if any.
+    fn get(&self, key: u64) -> Option<Vec<Value>>;
+    /// Stores the outputs for the key.
+    fn insert(&self, key: u64, outputs: Vec<Value>);
+}
+
+/// 64-bit FNV-1a hasher, used for cache keys.
+///
+/// Unlike [DefaultHasher](std::collections::hash_map::DefaultHasher), its output
+/// does not change between Rust releases, so keys stored by a [DiskCache] stay valid.
+/// Integers are hashed as little-endian 64-bit values, so keys are also the same
+/// on every platform.
+pub(crate) struct StableHasher(u64);
+
+impl StableHasher {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    pub fn new() -> Self {
+        Self(Self::OFFSET)
+    }
+}
+
+impl std::hash::Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write_u64(i.into());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write_u64(i.into());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_u64(i as u64);
+    }
+}
+
+/// [OutputCache] kept in memory, for the lifetime of the cache.
+#[derive(Debug, Default)]
+pub struct MemoryCache {
+    entries: Mutex<HashMap<u64, Vec<Value>>>,
+}
+
+impl MemoryCache {
+    /// Returns the number of cached entries.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes every entry.
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+impl OutputCache for MemoryCache {
+    fn get(&self, key: u64) -> Option<Vec<Value>> {
+        self.entries.lock().unwrap().get(&key).cloned()
+    }
+
+    fn insert(&self, key: u64, outputs: Vec<Value>) {
+        self.entries.lock().unwrap().insert(key, outputs);
+    }
+}
+
+/// [OutputCache] that stores each entry as a JSON file in a directory,
+/// so cached outputs are kept between runs.
+///
+/// Entries that cannot be read or written are treated as missing.
+/// The executor never caches outputs holding a [Value::Stream], which cannot be serialized.
+#[cfg(feature = "json")]
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    pub dir: std::path::PathBuf,
+}
+
+#[cfg(feature = "json")]
+impl DiskCache {
+    pub fn new(dir: impl Into<std::path::PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, key: u64) -> std::path::PathBuf {
+        self.dir.join(format!("{:016x}.json", key))
+    }
+}
+
+#[cfg(feature = "json")]
+impl OutputCache for DiskCache {
+    fn get(&self, key: u64) -> Option<Vec<Value>> {
+        let json = std::fs::read_to_string(self.path(key)).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    fn insert(&self, key: u64, outputs: Vec<Value>) {
+        let json = match serde_json::to_string(&outputs) {
+            Ok(json) => json,
+            Err(_) => return,
+        };
+
+        if std::fs::create_dir_all(&self.dir).is_ok() {
+            let _ = std::fs::write(self.path(key), json);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use crate::{
+        async_trait,
+        nodes::{AsyncNode, NodeError},
+        Diagnostic, ExecutionStep, ExecutionStepError, Executor, Graph, GraphEdge, GraphNode,
+        NodeData,
+    };
+
+    use super::*;
+
+    /// Counts how many times it has run, passing its inputs through.
+    struct Expensive(Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl AsyncNode for Expensive {
+        async fn run(&self, inputs: Vec<Value>) -> Result<Vec<Value>, NodeError> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(inputs)
+        }
+
+        fn data(&self) -> Option<NodeData> {
+            Some(NodeData {
+                name: "expensive".to_string(),
+                config: None,
+            })
+        }
+    }
+
+    /// Passes its inputs through, without any [NodeData].
+    struct Anonymous;
+
+    #[async_trait]
+    impl AsyncNode for Anonymous {
+        async fn run(&self, inputs: Vec<Value>) -> Result<Vec<Value>, NodeError> {
+            Ok(inputs)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pure_node() {
+        let mut graph = Graph::default();
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let node = graph.add_node(GraphNode::AsyncNode(Arc::new(Expensive(runs.clone()))));
+        let input = graph.add_node(GraphNode::Store(Value::USize(1)));
+        graph.add_edge(input, node, GraphEdge::DataMap(0));
+        let output = graph.add_node(GraphNode::Store(Value::Null));
+        graph.add_edge(node, output, GraphEdge::DataMap(0));
+
+        let mut executor = Executor {
+            cache: Some(Arc::new(MemoryCache::default())),
+            ..Default::default()
+        };
+        executor.node(node).pure = true;
+
+        executor.run(&mut graph, node).await.unwrap();
+        graph[output] = GraphNode::Store(Value::Null);
+        executor.run(&mut graph, node).await.unwrap();
+
+        // The second run reused the cached output.
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert!(matches!(graph[output], GraphNode::Store(Value::USize(1))));
+
+        // Changing the input runs the node again.
+        graph[input] = GraphNode::Store(Value::USize(2));
+        executor.run(&mut graph, node).await.unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+
+        // Nodes not marked as pure are always run.
+        executor.node(node).pure = false;
+        executor.run(&mut graph, node).await.unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_pure_uncacheable() {
+        let mut graph = Graph::default();
+        let node = graph.add_node(GraphNode::AsyncNode(Arc::new(Anonymous)));
+
+        let mut executor = Executor {
+            cache: Some(Arc::new(MemoryCache::default())),
+            ..Default::default()
+        };
+        executor.node(node).pure = true;
+
+        // Nodes without data cannot be told apart in the cache.
+        let res = executor.run(&mut graph, node).await;
+        assert!(matches!(
+            res,
+            Err(ExecutionStepError::InvalidGraph(diagnostics))
+                if diagnostics == vec![Diagnostic::Uncacheable(node)]
+        ));
+    }
+
+    #[test]
+    fn test_cache_key() {
+        let mut graph = Graph::default();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let expensive =
+            ExecutionStep(graph.add_node(GraphNode::AsyncNode(Arc::new(Expensive(runs.clone())))));
+        let other = ExecutionStep(graph.add_node(GraphNode::AsyncNode(Arc::new(Expensive(runs)))));
+        let anonymous = ExecutionStep(graph.add_node(GraphNode::AsyncNode(Arc::new(Anonymous))));
+
+        let key = expensive.cache_key(&graph, &[Value::USize(1)]);
+
+        // Keys do not change between Rust releases or platforms.
+        assert_eq!(key, Some(0x791f_9dc4_778b_49fb));
+
+        // Nodes with the same data share keys, wherever they are in the graph.
+        assert_eq!(other.cache_key(&graph, &[Value::USize(1)]), key);
+        assert_ne!(expensive.cache_key(&graph, &[Value::USize(2)]), key);
+
+        // Nodes without data are never cached.
+        assert_eq!(anonymous.cache_key(&graph, &[Value::USize(1)]), None);
+
+        // Equal floats have equal keys.
+        assert_eq!(
+            expensive.cache_key(&graph, &[Value::F64(-0.0)]),
+            expensive.cache_key(&graph, &[Value::F64(0.0)])
+        );
+    }
+
+    #[test]
+    fn test_memory_cache() {
+        let cache = MemoryCache::default();
+        assert_eq!(cache.get(1), None);
+
+        cache.insert(1, vec![Value::Bool(true)]);
+        assert_eq!(cache.get(1), Some(vec![Value::Bool(true)]));
+        assert_eq!(cache.len(), 1);
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_disk_cache() {
+        let dir = std::env::temp_dir().join(format!("lemon-cache-{}", std::process::id()));
+        let cache = DiskCache::new(&dir);
+        assert_eq!(cache.get(1), None);
+
+        cache.insert(1, vec![Value::String("cached".to_string())]);
+
+        // A new cache in the same directory sees the entry.
+        let cache = DiskCache::new(&dir);
+        assert_eq!(
+            cache.get(1),
+            Some(vec![Value::String("cached".to_string())])
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
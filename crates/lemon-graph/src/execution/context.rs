@@ -0,0 +1,229 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt::{Display, Formatter},
+    sync::{Arc, Mutex, MutexGuard, RwLock},
+};
+
+use petgraph::graph::NodeIndex;
+use tokio_util::sync::CancellationToken;
+
+use crate::{ExecutionObserver, OutputCache, Replay, Value};
+
+use super::limits::Budget;
+
+/// State shared by every node of a single execution.
+///
+/// Created by the [Executor](crate::Executor) for each run, and passed to nodes
+/// through their `run_with_context` methods.
+/// Cloning is cheap, and clones share the same variables and services.
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionContext {
+    /// Identifies this execution.
+    pub run_id: RunId,
+    /// Variables any node can read or write during the execution.
+    pub variables: Variables,
+    /// Token cancelled when the execution is cancelled.
+    pub cancel: CancellationToken,
+    /// Shared services, such as HTTP clients or backends.
+    pub services: Services,
+    /// Settings of the executor that created the context, used by nested
+    /// executions such as subgraphs.
+    pub(crate) inherited: Inherited,
+}
+
+impl ExecutionContext {
+    /// Locks the budget shared by every execution using the context.
+    pub(crate) fn budget(&self) -> MutexGuard<'_, Budget> {
+        self.inherited.budget.lock().unwrap()
+    }
+}
+
+/// Settings inherited by nested executions from the [Executor](crate::Executor)
+/// that created the context.
+#[derive(Clone, Default)]
+pub(crate) struct Inherited {
+    /// Budget shared by every execution using the context.
+    pub budget: Arc<Mutex<Budget>>,
+    /// Subgraph nodes leading to the graph being executed, see [ExecutionEvent](crate::ExecutionEvent).
+    /// Async nodes are given their own index at the end, so subgraphs can
+    /// pass it on to their inner execution.
+    pub path: Vec<NodeIndex>,
+    pub observers: Vec<Arc<dyn ExecutionObserver>>,
+    pub cache: Option<Arc<dyn OutputCache>>,
+    pub replay: Option<Replay>,
+    pub validate: bool,
+}
+
+impl std::fmt::Debug for Inherited {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Inherited")
+            .field("budget", &self.budget)
+            .field("path", &self.path)
+            .field("observers", &self.observers.len())
+            .field("cache", &self.cache.is_some())
+            .field("replay", &self.replay.is_some())
+            .field("validate", &self.validate)
+            .finish()
+    }
+}
+
+/// Randomly generated identifier of an execution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RunId(pub u64);
+
+impl RunId {
+    pub fn new() -> Self {
+        Self(rand::random())
+    }
+}
+
+impl Default for RunId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display for RunId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// Named values shared between nodes.
+#[derive(Debug, Clone, Default)]
+pub struct Variables(Arc<RwLock<HashMap<String, Value>>>);
+
+impl Variables {
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.0.read().unwrap().get(name).cloned()
+    }
+
+    /// Sets a variable, returning its previous value.
+    pub fn set(&self, name: impl Into<String>, value: Value) -> Option<Value> {
+        self.0.write().unwrap().insert(name.into(), value)
+    }
+
+    pub fn remove(&self, name: &str) -> Option<Value> {
+        self.0.write().unwrap().remove(name)
+    }
+}
+
+/// Shared services, stored by type.
+#[derive(Clone, Default)]
+pub struct Services(HashMap<TypeId, Arc<dyn Any + Send + Sync>>);
+
+impl Services {
+    /// Adds a service, replacing any other service of the same type.
+    pub fn insert<T: Send + Sync + 'static>(&mut self, service: T) {
+        self.0.insert(TypeId::of::<T>(), Arc::new(service));
+    }
+
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        let service = self.0.get(&TypeId::of::<T>())?.clone();
+        service.downcast().ok()
+    }
+}
+
+impl std::fmt::Debug for Services {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Services")
+            .field("len", &self.0.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        async_trait,
+        nodes::{AsyncNode, NodeError, SyncNode},
+        Executor, Graph, GraphNode,
+    };
+
+    use super::*;
+
+    struct Greeting(String);
+
+    /// Writes the run ID and a greeting from the services to variables.
+    struct Greet;
+
+    impl SyncNode for Greet {
+        fn run(&self, _inputs: Vec<Value>) -> Result<Vec<Value>, NodeError> {
+            Err(NodeError::InternalError("Requires a context".to_string()))
+        }
+
+        fn run_with_context(
+            &self,
+            ctx: &ExecutionContext,
+            _inputs: Vec<Value>,
+        ) -> Result<Vec<Value>, NodeError> {
+            let greeting = ctx.services.get::<Greeting>().unwrap();
+            ctx.variables
+                .set("greeting", Value::String(greeting.0.clone()));
+            ctx.variables
+                .set("run_id", Value::String(ctx.run_id.to_string()));
+            Ok(Vec::new())
+        }
+    }
+
+    /// Outputs the value of a variable.
+    struct Read(&'static str);
+
+    #[async_trait]
+    impl AsyncNode for Read {
+        async fn run(&self, _inputs: Vec<Value>) -> Result<Vec<Value>, NodeError> {
+            Ok(vec![Value::Null])
+        }
+
+        async fn run_with_context(
+            &self,
+            ctx: &ExecutionContext,
+            _inputs: Vec<Value>,
+        ) -> Result<Vec<Value>, NodeError> {
+            Ok(vec![ctx.variables.get(self.0).unwrap_or(Value::Null)])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_context() {
+        let mut graph = Graph::default();
+
+        let greet = graph.add_node(GraphNode::SyncNode(Box::new(Greet)));
+        let read = graph.add_node(GraphNode::AsyncNode(Arc::new(Read("greeting"))));
+        let output = graph.add_node(GraphNode::Store(Value::Null));
+        graph.add_edge(greet, read, crate::GraphEdge::ExecutionFlow(0));
+        graph.add_edge(read, output, crate::GraphEdge::DataMap(0));
+
+        let mut executor = Executor::default();
+        executor.services.insert(Greeting("Hello".to_string()));
+
+        let ctx = executor.context();
+        executor
+            .run_with_context(&mut graph, greet, ctx.clone())
+            .await
+            .unwrap();
+
+        assert!(matches!(&graph[output], GraphNode::Store(Value::String(s)) if s == "Hello"));
+        assert_eq!(
+            ctx.variables.get("run_id"),
+            Some(Value::String(ctx.run_id.to_string()))
+        );
+
+        // Each run has its own context.
+        assert_ne!(executor.context().run_id, ctx.run_id);
+        assert!(executor.context().variables.get("greeting").is_none());
+    }
+
+    #[test]
+    fn test_services() {
+        let mut services = Services::default();
+        assert!(services.get::<Greeting>().is_none());
+
+        services.insert(Greeting("Hi".to_string()));
+        services.insert(42usize);
+
+        assert_eq!(services.get::<Greeting>().unwrap().0, "Hi");
+        assert_eq!(*services.get::<usize>().unwrap(), 42);
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::HashSet;
+
+use petgraph::graph::NodeIndex;
+
+use crate::{ExecutionContext, ExecutionStep, ExecutionStepError, Graph, GraphNode};
+
+/// Executes a graph one [ExecutionStep] at a time, pausing at breakpoints.
+///
+/// While paused, stores can be inspected or modified through
+/// [graph_mut](Debugger::graph_mut) before resuming.
+/// Steps are run one at a time using [ExecutionStep::execute], without the
+/// node options of an [Executor](crate::Executor).
+///
+/// Stores with an incoming data flow edge are updated from their source when
+/// the step reading them runs, so modify the source store instead.
+pub struct Debugger<'a> {
+    graph: &'a mut Graph,
+    steps: Vec<ExecutionStep>,
+    /// Whether execution is paused at the breakpoint of the next step.
+    paused: bool,
+    /// Nodes to pause before running.
+    pub breakpoints: HashSet<NodeIndex>,
+    /// Context passed to each node.
+    pub context: ExecutionContext,
+}
+
+impl<'a> Debugger<'a> {
+    /// Prepares to execute the graph from the given node.
+    /// No steps are run until [step](Debugger::step) or [resume](Debugger::resume) is called.
+    pub fn new(graph: &'a mut Graph, start: NodeIndex) -> Self {
+        for node in graph.node_weights() {
+            if let GraphNode::FlowNode(node) = node {
+                node.reset();
+            }
+        }
+
+        Self {
+            graph,
+            steps: vec![ExecutionStep(start)],
+            paused: false,
+            breakpoints: HashSet::new(),
+            context: ExecutionContext::default(),
+        }
+    }
+
+    /// Adds a breakpoint, pausing before the node runs.
+    pub fn breakpoint(&mut self, node: impl Into<NodeIndex>) {
+        self.breakpoints.insert(node.into());
+    }
+
+    /// Returns the step that will run next, if any.
+    pub fn next_step(&self) -> Option<ExecutionStep> {
+        self.steps.last().copied()
+    }
+
+    /// Returns whether there are no steps left to run.
+    pub fn is_finished(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn graph(&self) -> &Graph {
+        self.graph
+    }
+
+    pub fn graph_mut(&mut self) -> &mut Graph {
+        self.graph
+    }
+
+    /// Runs the next step, returning it.
+    /// Returns `None` if there are no steps left to run.
+    pub async fn step(&mut self) -> Result<Option<ExecutionStep>, ExecutionStepError> {
+        let step = match self.steps.pop() {
+            Some(step) => step,
+            None => return Ok(None),
+        };
+
+        self.paused = false;
+
+        let next = step
+            .execute_with_context(self.graph, &self.context)
+            .await?
+            .collect::<Vec<_>>();
+        self.steps.extend(next);
+
+        Ok(Some(step))
+    }
+
+    /// Runs steps until the next step is at a breakpoint, returning it.
+    /// Returns `None` once there are no steps left to run.
+    ///
+    /// Resuming while paused at a breakpoint runs that step before continuing.
+    pub async fn resume(&mut self) -> Result<Option<ExecutionStep>, ExecutionStepError> {
+        loop {
+            match self.next_step() {
+                Some(step) if !self.paused && self.breakpoints.contains(&step.0) => {
+                    self.paused = true;
+                    return Ok(Some(step));
+                }
+                Some(_) => {
+                    self.step().await?;
+                }
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        nodes::{CallbackNode, ForEachNode, Node},
+        Value,
+    };
+
+    use super::*;
+
+    fn add_one(graph: &mut Graph) -> CallbackNode {
+        CallbackNode::new(graph, |value| match value {
+            Value::USize(value) => Value::USize(value + 1),
+            value => value,
+        })
+    }
+
+    /// a -> b -> c, each adding one to the output of the last.
+    fn chain(graph: &mut Graph) -> [CallbackNode; 3] {
+        let nodes = [add_one(graph), add_one(graph), add_one(graph)];
+
+        for pair in nodes.windows(2) {
+            pair[1].run_after(graph, pair[0].0);
+            let output = pair[0].output(graph).unwrap();
+            let input = pair[1].input(graph).unwrap();
+            input.set_input(graph, Some(output)).unwrap();
+        }
+
+        let input = nodes[0].input(graph).unwrap();
+        input.set_value(graph, Value::USize(0));
+
+        nodes
+    }
+
+    #[tokio::test]
+    async fn test_step() {
+        let mut graph = Graph::default();
+        let [a, b, c] = chain(&mut graph);
+
+        let mut debugger = Debugger::new(&mut graph, a.0);
+        assert_eq!(debugger.next_step(), Some(ExecutionStep(a.0)));
+
+        for node in [a, b, c] {
+            assert_eq!(debugger.step().await.unwrap(), Some(ExecutionStep(node.0)));
+        }
+
+        assert!(debugger.is_finished());
+        assert_eq!(debugger.step().await.unwrap(), None);
+
+        let output = c.output(&graph).unwrap();
+        assert_eq!(output.value(&graph), Some(&Value::USize(3)));
+    }
+
+    #[tokio::test]
+    async fn test_breakpoint() {
+        let mut graph = Graph::default();
+        let [a, b, c] = chain(&mut graph);
+
+        let mut debugger = Debugger::new(&mut graph, a.0);
+        debugger.breakpoint(a);
+        debugger.breakpoint(c);
+
+        assert_eq!(debugger.resume().await.unwrap(), Some(ExecutionStep(a.0)));
+
+        // Paused before c, after b has run.
+        let paused = debugger.resume().await.unwrap();
+        assert_eq!(paused, Some(ExecutionStep(c.0)));
+
+        let output = b.output(debugger.graph()).unwrap();
+        assert_eq!(output.value(debugger.graph()), Some(&Value::USize(2)));
+
+        // Change the value c will read before resuming.
+        output.set_value(debugger.graph_mut(), Value::USize(10));
+
+        assert_eq!(debugger.resume().await.unwrap(), None);
+
+        let output = c.output(&graph).unwrap();
+        assert_eq!(output.value(&graph), Some(&Value::USize(11)));
+    }
+
+    #[tokio::test]
+    async fn test_loop() {
+        let mut graph = Graph::default();
+
+        let for_each = ForEachNode::new(&mut graph);
+        let items = for_each.items(&graph).unwrap();
+        items.set_value(
+            &mut graph,
+            Value::Vec(vec![Value::USize(1), Value::USize(2)]),
+        );
+
+        let body = CallbackNode::new(&mut graph, |value| value);
+        for_each.body(&mut graph, body.0);
+
+        let mut debugger = Debugger::new(&mut graph, for_each.0);
+        debugger.breakpoint(body);
+
+        // Paused before the body, once for each item.
+        for item in [1, 2] {
+            assert_eq!(
+                debugger.resume().await.unwrap(),
+                Some(ExecutionStep(body.0))
+            );
+
+            let item_store = for_each.item(debugger.graph()).unwrap();
+            assert_eq!(
+                item_store.value(debugger.graph()),
+                Some(&Value::USize(item))
+            );
+        }
+
+        assert_eq!(debugger.resume().await.unwrap(), None);
+    }
+}
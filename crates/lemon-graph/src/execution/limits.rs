@@ -0,0 +1,250 @@
+use std::{
+    fmt::{Display, Formatter},
+    time::{Duration, Instant},
+};
+
+use crate::{ExecutionStep, ExecutionStepError};
+
+/// Limits on a single execution, to stop cyclic graphs from running away.
+///
+/// Limits are checked before each step starts. The time limit is also
+/// enforced while waiting for async nodes to finish.
+/// Use a [NodeOptions::timeout](crate::NodeOptions::timeout) to limit how long
+/// a single node may run for.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum number of steps to run in total.
+    pub max_steps: Option<usize>,
+    /// Maximum number of times each node may be run.
+    pub max_visits: Option<usize>,
+    /// Maximum time since execution started.
+    pub max_duration: Option<Duration>,
+}
+
+/// Limit that was exceeded, see [Limits].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Steps(usize),
+    Visits(usize),
+    Duration(Duration),
+}
+
+impl Display for Limit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Limit::Steps(max) => write!(f, "limit of {} steps", max),
+            Limit::Visits(max) => write!(f, "limit of {} visits per node", max),
+            Limit::Duration(max) => write!(f, "time limit of {:?}", max),
+        }
+    }
+}
+
+/// Work done so far by an execution, checked against its [Limits].
+///
+/// Shared with nested executions, such as subgraphs, so their steps count
+/// towards the same limits.
+#[derive(Debug, Default)]
+pub(crate) struct Budget {
+    limits: Limits,
+    started: Option<Instant>,
+    /// Last step to start.
+    last: Option<ExecutionStep>,
+    steps: usize,
+    next_id: usize,
+}
+
+impl Budget {
+    pub fn new(limits: Limits) -> Self {
+        Self {
+            limits,
+            ..Default::default()
+        }
+    }
+
+    /// Returns a new ID for a step, unique within the execution.
+    pub fn next_id(&mut self) -> usize {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /// Counts a step about to start, given the number of times its node has
+    /// been visited, failing if it would exceed a limit.
+    /// The time limit starts with the first step.
+    pub fn spend(&mut self, step: ExecutionStep, visits: usize) -> Result<(), ExecutionStepError> {
+        let limits = &self.limits;
+        let started = *self.started.get_or_insert_with(Instant::now);
+
+        let exceeded = |limit| ExecutionStepError::LimitExceeded {
+            limit,
+            node: step.0,
+        };
+
+        self.steps += 1;
+        self.last = Some(step);
+
+        if let Some(max) = limits.max_steps {
+            if self.steps > max {
+                return Err(exceeded(Limit::Steps(max)));
+            }
+        }
+
+        if let Some(max) = limits.max_visits {
+            if visits > max {
+                return Err(exceeded(Limit::Visits(max)));
+            }
+        }
+
+        if let Some(max) = limits.max_duration {
+            if started.elapsed() > max {
+                return Err(exceeded(Limit::Duration(max)));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns when the time limit runs out, if it has started, along with
+    /// the error to fail with, naming the last step to start.
+    pub fn deadline(&self) -> Option<(Instant, ExecutionStepError)> {
+        let max = self.limits.max_duration?;
+        let error = ExecutionStepError::LimitExceeded {
+            limit: Limit::Duration(max),
+            node: self.last?.0,
+        };
+
+        Some((self.started? + max, error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        nodes::{AsyncCallbackNode, CallbackNode, Node},
+        Executor, Graph, Value,
+    };
+
+    use super::*;
+
+    /// a -> b -> a, forever.
+    fn cycle(graph: &mut Graph) -> (CallbackNode, CallbackNode) {
+        let a = CallbackNode::new(graph, |value| value);
+        let b = CallbackNode::new(graph, |value| {
+            std::thread::sleep(Duration::from_millis(1));
+            value
+        });
+        b.run_after(graph, a.0);
+        a.run_after(graph, b.0);
+        (a, b)
+    }
+
+    async fn run_cycle(limits: Limits) -> (ExecutionStepError, CallbackNode, CallbackNode) {
+        let mut graph = Graph::default();
+        let (a, b) = cycle(&mut graph);
+
+        let executor = Executor {
+            limits,
+            ..Default::default()
+        };
+        let error = executor.run(&mut graph, a.0).await.unwrap_err();
+
+        (error, a, b)
+    }
+
+    #[tokio::test]
+    async fn test_max_steps() {
+        let (error, a, _) = run_cycle(Limits {
+            max_steps: Some(4),
+            ..Default::default()
+        })
+        .await;
+
+        // a, b, a, b, then stopped before the fifth step.
+        assert!(matches!(
+            error,
+            ExecutionStepError::LimitExceeded { limit: Limit::Steps(4), node } if node == a.0
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_max_visits() {
+        let (error, a, _) = run_cycle(Limits {
+            max_steps: Some(100),
+            max_visits: Some(2),
+            ..Default::default()
+        })
+        .await;
+
+        // a, b, a, b, a: the third visit to a.
+        assert!(matches!(
+            error,
+            ExecutionStepError::LimitExceeded { limit: Limit::Visits(2), node } if node == a.0
+        ));
+        assert_eq!(
+            error.to_string(),
+            format!("Exceeded limit of 2 visits per node at node {:?}", a.0)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_max_duration() {
+        let (error, _, _) = run_cycle(Limits {
+            max_duration: Some(Duration::from_millis(20)),
+            ..Default::default()
+        })
+        .await;
+
+        assert!(matches!(
+            error,
+            ExecutionStepError::LimitExceeded {
+                limit: Limit::Duration(_),
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_max_duration_while_running() {
+        let mut graph = Graph::default();
+        let slow = AsyncCallbackNode::new(&mut graph, &[], &[], |_| async {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            Ok::<_, String>(Vec::new())
+        });
+
+        let executor = Executor {
+            limits: Limits {
+                max_duration: Some(Duration::from_millis(20)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        // The node is stopped when the time runs out, rather than when it finishes.
+        let started = Instant::now();
+        let error = executor.run(&mut graph, slow.0).await.unwrap_err();
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(matches!(
+            error,
+            ExecutionStepError::LimitExceeded { limit: Limit::Duration(_), node } if node == slow.0
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_within_limits() {
+        let mut graph = Graph::default();
+        let node = CallbackNode::new(&mut graph, |_| Value::Bool(true));
+
+        let executor = Executor {
+            limits: Limits {
+                max_steps: Some(1),
+                max_visits: Some(1),
+                max_duration: Some(Duration::from_secs(10)),
+            },
+            ..Default::default()
+        };
+        executor.run(&mut graph, node.0).await.unwrap();
+
+        // Limits apply to each execution separately.
+        executor.run(&mut graph, node.0).await.unwrap();
+    }
+}
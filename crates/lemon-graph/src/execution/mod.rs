@@ -1,21 +1,1216 @@
+mod cache;
+mod context;
+mod debug;
+mod limits;
+mod observer;
+mod record;
+mod retry;
 mod step;
 
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+pub use cache::*;
+pub use context::*;
+pub use debug::*;
+use futures_util::{stream::FuturesUnordered, StreamExt};
+use limits::Budget;
+pub use limits::{Limit, Limits};
+pub use observer::*;
 use petgraph::graph::NodeIndex;
+pub use record::*;
+pub use retry::*;
 pub use step::*;
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+use crate::{
+    nodes::{AsyncNode, Flow},
+    validate, Diagnostic, Graph, GraphNode, Value,
+};
+
+/// Executes a graph, following [GraphEdge::ExecutionFlow](crate::GraphEdge::ExecutionFlow) edges.
+///
+/// Async nodes on independent branches can be run concurrently, up to the
+/// configured `concurrency`. Inputs are read when a step starts, and outputs are
+/// written when it finishes, so stores are only ever accessed by the executor itself.
+pub struct Executor {
+    /// Maximum number of async nodes to run at the same time.
+    /// Defaults to 1, which executes one step at a time.
+    pub concurrency: usize,
+    /// Whether to [validate] the graph before executing it.
+    pub validate: bool,
+    /// Token used to cancel execution.
+    /// Once cancelled, execution stops before the next step, and any running
+    /// async nodes are aborted.
+    pub cancel: CancellationToken,
+    /// Options for individual nodes.
+    pub nodes: HashMap<NodeIndex, NodeOptions>,
+    /// Observers notified of each step of execution.
+    pub observers: Vec<Arc<dyn ExecutionObserver>>,
+    /// Recorded outputs to use instead of running some nodes.
+    pub replay: Option<Replay>,
+    /// Cache for the outputs of nodes marked as pure.
+    pub cache: Option<Arc<dyn OutputCache>>,
+    /// Limits on each execution, such as the maximum number of steps.
+    /// Nested executions, such as subgraphs, count towards the same limits.
+    pub limits: Limits,
+    /// Services made available to nodes through their [ExecutionContext].
+    pub services: Services,
+}
+
+impl Default for Executor {
+    fn default() -> Self {
+        Self {
+            concurrency: 1,
+            validate: false,
+            cancel: CancellationToken::new(),
+            nodes: HashMap::new(),
+            observers: Vec::new(),
+            replay: None,
+            cache: None,
+            limits: Limits::default(),
+            services: Services::default(),
+        }
+    }
+}
+
+/// Options for executing a single node.
+#[derive(Debug, Default, Clone)]
+pub struct NodeOptions {
+    /// Maximum time an async node may run for.
+    /// With a retry policy, this applies to each attempt, and attempts that time
+    /// out are retried unless [RetryPolicy::timeouts] is disabled.
+    pub timeout: Option<Duration>,
+    /// Policy for retrying the node when it fails.
+    pub retry: Option<RetryPolicy>,
+    /// Whether the outputs of the node depend only on its inputs.
+    /// Outputs of pure nodes are reused from the executor's cache when their
+    /// inputs have not changed.
+    ///
+    /// Only nodes with [NodeData](crate::NodeData) can be cached, as it identifies
+    /// them across graphs sharing a cache. Marking a flow node, or a node without
+    /// data, as pure fails the execution with [Diagnostic::Uncacheable].
+    pub pure: bool,
+}
+
+/// One attempt at running an async node.
+#[derive(Clone, Copy)]
+struct Attempt {
+    step: ExecutionStep,
+    /// ID of the step, see [ExecutionEvent].
+    id: usize,
+    /// Number of this attempt, starting at 1.
+    number: usize,
+    /// When the first attempt started.
+    started: Instant,
+    /// Key to cache the outputs with, if the node is pure.
+    key: Option<u64>,
+    /// Loop the step is part of the body of, see [Queue].
+    frame: Option<usize>,
+}
+
+/// Result of an [Attempt].
+struct Attempted {
+    attempt: Attempt,
+    /// Inputs to retry with, kept if the node has a retry policy.
+    inputs: Option<Vec<Value>>,
+    res: Result<Vec<Value>, ExecutionStepError>,
+}
+
+/// What a [Running] future is waiting for.
+enum Ready {
+    /// An attempt at running an async node finished.
+    Attempted(Attempted),
+    /// The backoff before retrying a sync node is over.
+    Retry(Attempt, Vec<Value>),
+}
+
+/// Future run concurrently with other steps.
+type Running = Pin<Box<dyn Future<Output = Ready> + Send>>;
+
+/// What happened after a step was started.
+enum Started {
+    /// The node finished, followed by the given steps.
+    Finished(Vec<ExecutionStep>),
+    /// An async node is running, or a node is waiting to be retried.
+    Running(Running),
+    /// A flow node is looping, and its body needs to run first.
+    Loop(Vec<ExecutionStep>),
+}
+
+/// Steps of an execution waiting to start, and the async nodes running.
+///
+/// The steps of a loop body are tracked by a [LoopFrame], so they run alongside
+/// other branches, and the loop is repeated once all of them have finished.
+#[derive(Default)]
+struct Queue {
+    /// Steps waiting to start, along with the loop they are part of.
+    steps: Vec<(ExecutionStep, Option<usize>)>,
+    running: FuturesUnordered<Running>,
+    /// Loops running their body, by ID.
+    loops: HashMap<usize, LoopFrame>,
+    next_loop: usize,
+}
+
+/// A flow node waiting for the body of its loop to finish.
+struct LoopFrame {
+    step: ExecutionStep,
+    /// Loop the flow node is itself part of.
+    parent: Option<usize>,
+    /// Number of steps of the body that have not finished yet.
+    pending: usize,
+}
+
+impl Queue {
+    /// Adds steps that are part of the given loop.
+    fn push(&mut self, steps: impl IntoIterator<Item = ExecutionStep>, frame: Option<usize>) {
+        for step in steps {
+            if let Some(frame) = frame.and_then(|id| self.loops.get_mut(&id)) {
+                frame.pending += 1;
+            }
+
+            self.steps.push((step, frame));
+        }
+    }
+
+    /// Marks a step of the given loop as finished, once the steps following it
+    /// have been added.
+    /// When the whole body has finished, the loop is repeated.
+    fn finish(&mut self, frame: Option<usize>) {
+        let Some(id) = frame else {
+            return;
+        };
+
+        let Some(frame) = self.loops.get_mut(&id) else {
+            return;
+        };
+
+        frame.pending -= 1;
+
+        if frame.pending == 0 {
+            let frame = self.loops.remove(&id).unwrap();
+            self.steps.push((frame.step, frame.parent));
+        }
+    }
 
-use crate::Graph;
+    /// Starts the body of a loop, within the given loop.
+    fn start_loop(&mut self, step: ExecutionStep, frame: Option<usize>, body: Vec<ExecutionStep>) {
+        let id = self.next_loop;
+        self.next_loop += 1;
 
-pub struct Executor;
+        // Count the loop itself until its body is added, so an empty body
+        // repeats it straight away.
+        let frame = LoopFrame {
+            step,
+            parent: frame,
+            pending: 1,
+        };
+        self.loops.insert(id, frame);
+        self.push(body, Some(id));
+        self.finish(Some(id));
+    }
+}
 
 impl Executor {
+    /// Returns the options for the given node, to be modified.
+    pub fn node(&mut self, node: impl Into<NodeIndex>) -> &mut NodeOptions {
+        self.nodes.entry(node.into()).or_default()
+    }
+
+    /// Executes the graph with the default settings.
     pub async fn execute(graph: &mut Graph, start: NodeIndex) -> Result<(), ExecutionStepError> {
-        let mut steps = vec![ExecutionStep(start)];
+        Self::default().run(graph, start).await
+    }
+
+    /// Executes the graph, starting from the given node.
+    pub async fn run(&self, graph: &mut Graph, start: NodeIndex) -> Result<(), ExecutionStepError> {
+        self.run_with_context(graph, start, self.context()).await
+    }
+
+    /// Creates the context for a new execution, with a new run ID and no variables.
+    pub fn context(&self) -> ExecutionContext {
+        ExecutionContext {
+            run_id: RunId::new(),
+            variables: Variables::default(),
+            cancel: self.cancel.clone(),
+            services: self.services.clone(),
+            inherited: Inherited {
+                budget: Arc::new(Mutex::new(Budget::new(self.limits.clone()))),
+                path: Vec::new(),
+                observers: self.observers.clone(),
+                cache: self.cache.clone(),
+                replay: self.replay.clone(),
+                validate: self.validate,
+            },
+        }
+    }
+
+    /// Executes the graph with the given context, starting from the given node.
+    /// Execution is cancelled using the token of the context, rather than the executor,
+    /// and is subject to the limits of the executor that created the context.
+    pub async fn run_with_context(
+        &self,
+        graph: &mut Graph,
+        start: NodeIndex,
+        ctx: ExecutionContext,
+    ) -> Result<(), ExecutionStepError> {
+        let mut diagnostics = if self.validate {
+            validate(graph, start)
+        } else {
+            Vec::new()
+        };
+
+        diagnostics.extend(self.uncacheable(graph).map(Diagnostic::Uncacheable));
+
+        if !diagnostics.is_empty() {
+            return Err(ExecutionStepError::InvalidGraph(diagnostics));
+        }
+
+        for node in graph.node_weights() {
+            if let GraphNode::FlowNode(node) = node {
+                node.reset();
+            }
+        }
+
+        self.run_steps(graph, ExecutionStep(start), &ctx).await
+    }
+
+    /// Returns the nodes marked as pure that cannot be cached, sorted by index.
+    fn uncacheable(&self, graph: &Graph) -> impl Iterator<Item = NodeIndex> {
+        let mut nodes = self
+            .nodes
+            .iter()
+            .filter(|(_, opts)| opts.pure)
+            .map(|(idx, _)| *idx)
+            .filter(|idx| {
+                graph.node_weight(*idx).is_some_and(|node| {
+                    matches!(node, GraphNode::FlowNode(_)) || node.data().is_none()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        nodes.sort();
+        nodes.into_iter()
+    }
+
+    /// Notifies all observers of an event.
+    fn emit(&self, event: ExecutionEvent) {
+        for observer in &self.observers {
+            observer.on_event(&event);
+        }
+    }
+
+    /// Notifies observers that a step failed, whether or not it is recovered
+    /// from, and returns the error.
+    fn fail(
+        &self,
+        ctx: &ExecutionContext,
+        step: ExecutionStep,
+        id: usize,
+        error: ExecutionStepError,
+    ) -> ExecutionStepError {
+        self.emit(ExecutionEvent::StepFailed {
+            id,
+            path: &ctx.inherited.path,
+            node: step.0,
+            error: &error,
+        });
+        error
+    }
+
+    /// Notifies observers that a step failed, then continues through its error
+    /// flows if it has any, returning their steps.
+    /// Otherwise, returns the error.
+    fn recover(
+        &self,
+        graph: &mut Graph,
+        ctx: &ExecutionContext,
+        step: ExecutionStep,
+        id: usize,
+        error: ExecutionStepError,
+    ) -> Result<Vec<ExecutionStep>, ExecutionStepError> {
+        let error = self.fail(ctx, step, id, error);
+
+        match step.handle_error(graph, &error) {
+            Some(next) => {
+                warn!("Node {:?} failed, following error flow: {}", step.0, error);
+                Ok(next)
+            }
+            None => Err(error),
+        }
+    }
+
+    /// Writes the outputs of a step, and notifies observers that it finished.
+    fn finish(
+        &self,
+        graph: &mut Graph,
+        ctx: &ExecutionContext,
+        step: ExecutionStep,
+        id: usize,
+        outputs: Vec<Value>,
+        started: Instant,
+    ) {
+        if !self.observers.is_empty() {
+            self.emit(ExecutionEvent::OutputsWritten {
+                id,
+                path: &ctx.inherited.path,
+                node: step.0,
+                outputs: &outputs,
+            });
+        }
+
+        step.write_outputs(graph, outputs);
+
+        self.emit(ExecutionEvent::StepFinished {
+            id,
+            path: &ctx.inherited.path,
+            node: step.0,
+            duration: started.elapsed(),
+        });
+    }
+
+    /// Returns the recorded outputs of a step, if its node is replayed.
+    fn replayed(
+        &self,
+        graph: &Graph,
+        ctx: &ExecutionContext,
+        step: ExecutionStep,
+    ) -> Option<Result<Vec<Value>, ExecutionStepError>> {
+        let replay = self.replay.as_ref()?;
+        let path = &ctx.inherited.path;
+
+        if matches!(graph.node_weight(step.0), Some(GraphNode::FlowNode(_)))
+            || !replay.contains(path, step.0)
+        {
+            return None;
+        }
+
+        Some(
+            replay
+                .next(path, step.0)
+                .ok_or(ExecutionStepError::MissingReplay(step.0)),
+        )
+    }
+
+    /// Returns the key to cache the outputs of a step with, if its node is pure.
+    fn cache_key(&self, graph: &Graph, step: ExecutionStep, inputs: &[Value]) -> Option<u64> {
+        self.cache.as_ref()?;
+
+        let pure = self.nodes.get(&step.0).is_some_and(|opts| opts.pure);
+
+        if !pure || matches!(graph.node_weight(step.0), Some(GraphNode::FlowNode(_))) {
+            return None;
+        }
+
+        step.cache_key(graph, inputs)
+    }
+
+    /// Stores the outputs of a step in the cache.
+    /// Streams are not cached, as they are consumed as they are read.
+    fn cache_outputs(&self, key: Option<u64>, outputs: &[Value]) {
+        if let (Some(cache), Some(key)) = (&self.cache, key) {
+            if !outputs
+                .iter()
+                .any(|output| matches!(output, Value::Stream(_)))
+            {
+                cache.insert(key, outputs.to_vec());
+            }
+        }
+    }
+
+    /// Returns whether a node should be run again, after failing the given attempt.
+    fn should_retry(
+        &self,
+        step: ExecutionStep,
+        attempt: usize,
+        error: &ExecutionStepError,
+    ) -> bool {
+        let retry = match self.nodes.get(&step.0).and_then(|opts| opts.retry.as_ref()) {
+            Some(retry) => retry,
+            None => return false,
+        };
+
+        match error {
+            ExecutionStepError::NodeError(error) => retry.should_retry(attempt, error),
+            ExecutionStepError::Timeout(_) => retry.should_retry_timeout(attempt),
+            _ => false,
+        }
+    }
+
+    /// Returns the delay before running a node for the given attempt.
+    fn backoff(&self, step: ExecutionStep, attempt: usize) -> Option<Duration> {
+        let retry = self.nodes.get(&step.0)?.retry.as_ref()?;
+        (attempt > 1).then(|| retry.backoff(attempt - 1))
+    }
+
+    /// Runs an async node, waiting out the backoff first if this is a retry.
+    fn run_async(
+        &self,
+        node: &Arc<dyn AsyncNode>,
+        ctx: &ExecutionContext,
+        inputs: Vec<Value>,
+        attempt: Attempt,
+    ) -> impl Future<Output = Ready> + Send + 'static {
+        let step = attempt.step;
+        let opts = self.nodes.get(&step.0);
+        let timeout = opts.and_then(|opts| opts.timeout);
+        let retained = opts
+            .is_some_and(|opts| opts.retry.is_some())
+            .then(|| inputs.clone());
+        let backoff = self.backoff(step, attempt.number);
+        let node = node.clone();
+        let mut ctx = ctx.clone();
+        ctx.inherited.path.push(step.0);
+
+        async move {
+            let future = node.run_with_context(&ctx, inputs);
+
+            if let Some(backoff) = backoff {
+                tokio::time::sleep(backoff).await;
+            }
+
+            let res = match timeout {
+                Some(timeout) => tokio::time::timeout(timeout, future)
+                    .await
+                    .map_err(|_| ExecutionStepError::Timeout(step.0))
+                    .and_then(|res| res.map_err(ExecutionStepError::from)),
+                None => future.await.map_err(ExecutionStepError::from),
+            };
+
+            Ready::Attempted(Attempted {
+                attempt,
+                inputs: retained,
+                res,
+            })
+        }
+    }
+
+    /// Runs a sync node.
+    /// If it fails and should be retried, the retry is scheduled after the backoff,
+    /// so other nodes keep running in the meantime.
+    fn run_sync(
+        &self,
+        graph: &mut Graph,
+        ctx: &ExecutionContext,
+        attempt: Attempt,
+        inputs: Vec<Value>,
+    ) -> Result<Started, ExecutionStepError> {
+        let step = attempt.step;
+
+        let Some(GraphNode::SyncNode(node)) = graph.node_weight(step.0) else {
+            return Err(ExecutionStepError::InvalidWeight);
+        };
+
+        match node
+            .run_with_context(ctx, inputs.clone())
+            .map_err(ExecutionStepError::from)
+        {
+            Ok(outputs) => {
+                self.cache_outputs(attempt.key, &outputs);
+                self.finish(graph, ctx, step, attempt.id, outputs, attempt.started);
+                Ok(Started::Finished(
+                    step.next_steps(graph, Flow::Continue).collect(),
+                ))
+            }
+            Err(error) if self.should_retry(step, attempt.number, &error) => {
+                warn!("Retrying node {:?}: {}", step.0, error);
+
+                let attempt = Attempt {
+                    number: attempt.number + 1,
+                    ..attempt
+                };
+                let backoff = self.backoff(step, attempt.number).unwrap_or_default();
+
+                Ok(Started::Running(Box::pin(async move {
+                    tokio::time::sleep(backoff).await;
+                    Ready::Retry(attempt, inputs)
+                })))
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Queues the steps following a step that was started, or recovers from its failure.
+    fn settle(
+        &self,
+        graph: &mut Graph,
+        ctx: &ExecutionContext,
+        queue: &mut Queue,
+        attempt: Attempt,
+        res: Result<Started, ExecutionStepError>,
+    ) -> Result<(), ExecutionStepError> {
+        match res {
+            Ok(Started::Finished(next)) => {
+                queue.push(next, attempt.frame);
+                queue.finish(attempt.frame);
+            }
+            Ok(Started::Running(future)) => queue.running.push(future),
+            Ok(Started::Loop(body)) => queue.start_loop(attempt.step, attempt.frame, body),
+            Err(error) => {
+                let next = self.recover(graph, ctx, attempt.step, attempt.id, error)?;
+                queue.push(next, attempt.frame);
+                queue.finish(attempt.frame);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Starts running a step.
+    fn start(
+        &self,
+        graph: &mut Graph,
+        ctx: &ExecutionContext,
+        visits: &mut HashMap<NodeIndex, usize>,
+        attempt: Attempt,
+    ) -> Result<Started, ExecutionStepError> {
+        let Attempt {
+            step, id, started, ..
+        } = attempt;
+
+        let visited = visits.entry(step.0).or_default();
+        *visited += 1;
+        ctx.budget().spend(step, *visited)?;
+
+        let inputs = step.read_inputs(graph)?;
+
+        self.emit(ExecutionEvent::InputsResolved {
+            id,
+            path: &ctx.inherited.path,
+            node: step.0,
+            inputs: &inputs,
+        });
+
+        if let Some(outputs) = self.replayed(graph, ctx, step) {
+            self.finish(graph, ctx, step, id, outputs?, started);
+            return Ok(Started::Finished(
+                step.next_steps(graph, Flow::Continue).collect(),
+            ));
+        }
+
+        let key = self.cache_key(graph, step, &inputs);
+        let cached = key.and_then(|key| self.cache.as_ref()?.get(key));
+
+        if let Some(outputs) = cached {
+            self.finish(graph, ctx, step, id, outputs, started);
+            return Ok(Started::Finished(
+                step.next_steps(graph, Flow::Continue).collect(),
+            ));
+        }
+
+        let attempt = Attempt { key, ..attempt };
+
+        let node = graph
+            .node_weight(step.0)
+            .ok_or(ExecutionStepError::NoWeight)?;
+
+        match node {
+            GraphNode::AsyncNode(node) => {
+                let future = self.run_async(node, ctx, inputs, attempt);
+                Ok(Started::Running(Box::pin(future)))
+            }
+            GraphNode::SyncNode(_) => self.run_sync(graph, ctx, attempt, inputs),
+            GraphNode::FlowNode(node) => {
+                let (outputs, flow) = node.run_with_context(ctx, inputs)?;
+                self.finish(graph, ctx, step, id, outputs, started);
+
+                match flow {
+                    Flow::Loop(output) => {
+                        let body = step.next_steps(graph, Flow::Output(output));
+                        Ok(Started::Loop(body.collect()))
+                    }
+                    flow => Ok(Started::Finished(step.next_steps(graph, flow).collect())),
+                }
+            }
+            _ => Err(ExecutionStepError::InvalidWeight),
+        }
+    }
+
+    /// Executes a step, and all steps that follow it, to completion.
+    async fn run_steps(
+        &self,
+        graph: &mut Graph,
+        start: ExecutionStep,
+        ctx: &ExecutionContext,
+    ) -> Result<(), ExecutionStepError> {
+        let mut queue = Queue::default();
+        queue.push([start], None);
+
+        let mut visits = HashMap::new();
+
+        loop {
+            while queue.running.len() < self.concurrency.max(1) {
+                if ctx.cancel.is_cancelled() {
+                    return Err(ExecutionStepError::Cancelled);
+                }
+
+                let (step, frame) = match queue.steps.pop() {
+                    Some(step) => step,
+                    None => break,
+                };
+
+                let attempt = Attempt {
+                    step,
+                    id: ctx.budget().next_id(),
+                    number: 1,
+                    started: Instant::now(),
+                    key: None,
+                    frame,
+                };
+                self.emit(ExecutionEvent::StepStarted {
+                    id: attempt.id,
+                    path: &ctx.inherited.path,
+                    node: step.0,
+                });
+
+                let res = self.start(graph, ctx, &mut visits, attempt);
+                self.settle(graph, ctx, &mut queue, attempt, res)?;
+            }
+
+            // Stop waiting for running nodes once the time limit runs out.
+            let deadline = ctx.budget().deadline();
+            let timed_out = async move {
+                match deadline {
+                    Some((deadline, error)) => {
+                        tokio::time::sleep_until(deadline.into()).await;
+                        error
+                    }
+                    None => std::future::pending().await,
+                }
+            };
+
+            // Check for cancellation first, as nodes sharing the token may
+            // finish with an error at the same time.
+            let next = tokio::select! {
+                biased;
+                _ = ctx.cancel.cancelled() => return Err(ExecutionStepError::Cancelled),
+                next = queue.running.next() => next,
+                error = timed_out => return Err(error),
+            };
+
+            let Attempted {
+                attempt,
+                inputs,
+                res,
+            } = match next {
+                Some(Ready::Attempted(attempted)) => attempted,
+                Some(Ready::Retry(attempt, inputs)) => {
+                    let res = self.run_sync(graph, ctx, attempt, inputs);
+                    self.settle(graph, ctx, &mut queue, attempt, res)?;
+                    continue;
+                }
+                None => break,
+            };
+            let step = attempt.step;
 
-        while let Some(step) = steps.pop() {
-            let next_steps = step.execute(graph).await?;
-            steps.extend(next_steps);
+            let res = match res {
+                Ok(outputs) => {
+                    self.cache_outputs(attempt.key, &outputs);
+                    self.finish(graph, ctx, step, attempt.id, outputs, attempt.started);
+                    Ok(Started::Finished(
+                        step.next_steps(graph, Flow::Continue).collect(),
+                    ))
+                }
+                Err(error) if self.should_retry(step, attempt.number, &error) => {
+                    warn!("Retrying node {:?}: {}", step.0, error);
+
+                    match graph.node_weight(step.0) {
+                        Some(GraphNode::AsyncNode(node)) => {
+                            let inputs = inputs.unwrap_or_default();
+                            let attempt = Attempt {
+                                number: attempt.number + 1,
+                                ..attempt
+                            };
+                            let future = self.run_async(node, ctx, inputs, attempt);
+                            Ok(Started::Running(Box::pin(future)))
+                        }
+                        _ => Err(ExecutionStepError::InvalidWeight),
+                    }
+                }
+                Err(error) => Err(error),
+            };
+
+            self.settle(graph, ctx, &mut queue, attempt, res)?;
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use crate::{
+        nodes::{AsyncNode, CallbackNode, ForEachNode, Node, NodeError, SyncNode},
+        GraphEdge, Value,
+    };
+
+    use super::*;
+    use crate::async_trait;
+
+    #[derive(Default)]
+    struct Counter {
+        active: AtomicUsize,
+        max: AtomicUsize,
+        total: AtomicUsize,
+    }
+
+    struct CountingNode(Arc<Counter>);
+
+    #[async_trait]
+    impl AsyncNode for CountingNode {
+        async fn run(&self, _inputs: Vec<Value>) -> Result<Vec<Value>, NodeError> {
+            let counter = &self.0;
+
+            let active = counter.active.fetch_add(1, Ordering::SeqCst) + 1;
+            counter.max.fetch_max(active, Ordering::SeqCst);
+
+            tokio::time::sleep(Duration::from_millis(10)).await;
+
+            counter.active.fetch_sub(1, Ordering::SeqCst);
+            counter.total.fetch_add(1, Ordering::SeqCst);
+
+            Ok(Vec::new())
+        }
+    }
+
+    fn fan_out(branches: usize) -> (Graph, NodeIndex, Arc<Counter>) {
+        let mut graph = Graph::default();
+        let counter = Arc::new(Counter::default());
+
+        let start = CallbackNode::new(&mut graph, |value| value);
+
+        for _ in 0..branches {
+            let node = graph.add_node(GraphNode::AsyncNode(Arc::new(CountingNode(
+                counter.clone(),
+            ))));
+            start.run_before(&mut graph, node);
+        }
+
+        (graph, start.0, counter)
+    }
+
+    #[tokio::test]
+    async fn test_sequential() {
+        let (mut graph, start, counter) = fan_out(3);
+
+        Executor::execute(&mut graph, start).await.unwrap();
+
+        assert_eq!(counter.total.load(Ordering::SeqCst), 3);
+        assert_eq!(counter.max.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_loop_concurrent() {
+        let (mut graph, start, counter) = fan_out(1);
+
+        // The loop body runs alongside the async sibling of the loop.
+        let for_each = ForEachNode::new(&mut graph);
+        for_each.run_after(&mut graph, start);
+        let items = for_each.items(&graph).unwrap();
+        items.set_value(
+            &mut graph,
+            Value::Vec(vec![Value::USize(1), Value::USize(2)]),
+        );
+
+        let body = graph.add_node(GraphNode::AsyncNode(Arc::new(CountingNode(
+            counter.clone(),
+        ))));
+        for_each.body(&mut graph, body);
+
+        let executor = Executor {
+            concurrency: 2,
+            ..Default::default()
+        };
+        executor.run(&mut graph, start).await.unwrap();
+
+        assert_eq!(counter.total.load(Ordering::SeqCst), 3);
+        assert_eq!(counter.max.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_spawn() {
+        let (mut graph, start, counter) = fan_out(3);
+
+        let executor = Arc::new(Executor {
+            concurrency: 3,
+            ..Default::default()
+        });
+
+        tokio::spawn(async move { executor.run(&mut graph, start).await })
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(counter.total.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent() {
+        let (mut graph, start, counter) = fan_out(3);
+
+        let executor = Executor {
+            concurrency: 2,
+            ..Default::default()
+        };
+        executor.run(&mut graph, start).await.unwrap();
+
+        assert_eq!(counter.total.load(Ordering::SeqCst), 3);
+        assert_eq!(counter.max.load(Ordering::SeqCst), 2);
+    }
+
+    struct Sleep(Duration);
+
+    #[async_trait]
+    impl AsyncNode for Sleep {
+        async fn run(&self, _inputs: Vec<Value>) -> Result<Vec<Value>, NodeError> {
+            tokio::time::sleep(self.0).await;
+            Ok(Vec::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let mut graph = Graph::default();
+        let node = graph.add_node(GraphNode::AsyncNode(Arc::new(Sleep(Duration::from_secs(
+            10,
+        )))));
+
+        let mut executor = Executor::default();
+        executor.node(node).timeout = Some(Duration::from_millis(10));
+
+        let res = executor.run(&mut graph, node).await;
+        assert!(matches!(res, Err(ExecutionStepError::Timeout(idx)) if idx == node));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_before_start() {
+        let (mut graph, start, counter) = fan_out(1);
+
+        let executor = Executor::default();
+        executor.cancel.cancel();
+
+        let res = executor.run(&mut graph, start).await;
+        assert!(matches!(res, Err(ExecutionStepError::Cancelled)));
+        assert_eq!(counter.total.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_running() {
+        let mut graph = Graph::default();
+        let node = graph.add_node(GraphNode::AsyncNode(Arc::new(Sleep(Duration::from_secs(
+            10,
+        )))));
+
+        let executor = Executor::default();
+
+        let cancel = executor.cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            cancel.cancel();
+        });
+
+        let res = tokio::time::timeout(Duration::from_secs(5), executor.run(&mut graph, node))
+            .await
+            .unwrap();
+        assert!(matches!(res, Err(ExecutionStepError::Cancelled)));
+    }
+
+    /// Sleeps for a long time on its first run only.
+    struct SlowStart(Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl AsyncNode for SlowStart {
+        async fn run(&self, _inputs: Vec<Value>) -> Result<Vec<Value>, NodeError> {
+            if self.0.fetch_add(1, Ordering::SeqCst) == 0 {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+            }
+            Ok(Vec::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_timeout() {
+        let mut graph = Graph::default();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let node = graph.add_node(GraphNode::AsyncNode(Arc::new(SlowStart(runs.clone()))));
+
+        let mut executor = Executor::default();
+        executor.node(node).timeout = Some(Duration::from_millis(10));
+        executor.node(node).retry = Some(RetryPolicy {
+            initial_backoff: Duration::from_millis(1),
+            ..Default::default()
+        });
+        executor.run(&mut graph, node).await.unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+
+        // Timeouts are not retried when disabled.
+        runs.store(0, Ordering::SeqCst);
+        executor.node(node).retry.as_mut().unwrap().timeouts = false;
+
+        let res = executor.run(&mut graph, node).await;
+        assert!(matches!(res, Err(ExecutionStepError::Timeout(idx)) if idx == node));
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+
+    /// Fails with the given error until it has been run `failures` times.
+    struct Flaky {
+        runs: Arc<AtomicUsize>,
+        failures: usize,
+        error: fn() -> NodeError,
+    }
+
+    #[async_trait]
+    impl AsyncNode for Flaky {
+        async fn run(&self, inputs: Vec<Value>) -> Result<Vec<Value>, NodeError> {
+            SyncNode::run(self, inputs)
+        }
+    }
+
+    async fn run_flaky(
+        failures: usize,
+        error: fn() -> NodeError,
+    ) -> (Result<(), ExecutionStepError>, usize) {
+        let mut graph = Graph::default();
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let node = graph.add_node(GraphNode::AsyncNode(Arc::new(Flaky {
+            runs: runs.clone(),
+            failures,
+            error,
+        })));
+
+        let mut executor = Executor::default();
+        executor.node(node).retry = Some(RetryPolicy {
+            initial_backoff: Duration::from_millis(1),
+            ..Default::default()
+        });
+
+        let res = executor.run(&mut graph, node).await;
+        (res, runs.load(Ordering::SeqCst))
+    }
+
+    fn internal_error() -> NodeError {
+        NodeError::InternalError("Unavailable".to_string())
+    }
+
+    #[tokio::test]
+    async fn test_retry() {
+        let (res, runs) = run_flaky(2, internal_error).await;
+        assert!(res.is_ok());
+        assert_eq!(runs, 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_exhausted() {
+        let (res, runs) = run_flaky(3, internal_error).await;
+        assert!(matches!(
+            res,
+            Err(ExecutionStepError::NodeError(NodeError::InternalError(_)))
+        ));
+        assert_eq!(runs, 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_not_retryable() {
+        let (res, runs) = run_flaky(1, || NodeError::MissingInput(0)).await;
+        assert!(matches!(
+            res,
+            Err(ExecutionStepError::NodeError(NodeError::MissingInput(0)))
+        ));
+        assert_eq!(runs, 1);
+    }
+
+    impl SyncNode for Flaky {
+        fn run(&self, inputs: Vec<Value>) -> Result<Vec<Value>, NodeError> {
+            let runs = self.runs.fetch_add(1, Ordering::SeqCst) + 1;
+
+            if runs > self.failures {
+                Ok(inputs)
+            } else {
+                Err((self.error)())
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_sync() {
+        let mut graph = Graph::default();
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let node = graph.add_node(GraphNode::SyncNode(Box::new(Flaky {
+            runs: runs.clone(),
+            failures: 1,
+            error: internal_error,
+        })));
+
+        let mut executor = Executor::default();
+        executor.node(node).retry = Some(RetryPolicy {
+            initial_backoff: Duration::from_millis(1),
+            ..Default::default()
+        });
+        executor.run(&mut graph, node).await.unwrap();
+
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+
+    /// Sync node that fails once, recording how many siblings finished before its retry.
+    struct Waiting {
+        counter: Arc<Counter>,
+        seen: Arc<AtomicUsize>,
+    }
+
+    impl SyncNode for Waiting {
+        fn run(&self, _inputs: Vec<Value>) -> Result<Vec<Value>, NodeError> {
+            if self.seen.swap(1, Ordering::SeqCst) == 0 {
+                return Err(internal_error());
+            }
+
+            let total = self.counter.total.load(Ordering::SeqCst);
+            self.seen.store(total + 1, Ordering::SeqCst);
+            Ok(Vec::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_sync_concurrent() {
+        let (mut graph, start, counter) = fan_out(1);
+
+        // The async sibling finishes while the sync node waits to be retried.
+        let seen = Arc::new(AtomicUsize::new(0));
+        let node = graph.add_node(GraphNode::SyncNode(Box::new(Waiting {
+            counter: counter.clone(),
+            seen: seen.clone(),
+        })));
+        graph.add_edge(start, node, GraphEdge::ExecutionFlow(0));
+
+        let mut executor = Executor {
+            concurrency: 2,
+            ..Default::default()
+        };
+        executor.node(node).retry = Some(RetryPolicy {
+            initial_backoff: Duration::from_millis(100),
+            jitter: false,
+            ..Default::default()
+        });
+        executor.run(&mut graph, start).await.unwrap();
+
+        assert_eq!(counter.total.load(Ordering::SeqCst), 1);
+        assert_eq!(seen.load(Ordering::SeqCst), 2);
+    }
+
+    /// Node that always fails, with an error flow to a handler reading the error.
+    fn failing(graph: &mut Graph) -> (CallbackNode, CallbackNode) {
+        let node = CallbackNode::try_new(graph, |_| Err::<Value, _>("Unavailable"));
+        let handler = CallbackNode::new(graph, |value| value);
+        node.on_error(graph, handler.0);
+
+        let error = node.error_store(graph);
+        let input = handler.input(graph).unwrap();
+        input.set_input(graph, Some(error)).unwrap();
+
+        (node, handler)
+    }
+
+    #[tokio::test]
+    async fn test_error_flow() {
+        let mut graph = Graph::default();
+        let (node, handler) = failing(&mut graph);
+
+        let after = CallbackNode::new(&mut graph, |_| Value::Bool(true));
+        after.run_after(&mut graph, node.0);
+
+        Executor::default().run(&mut graph, node.0).await.unwrap();
+
+        let output = handler.output(&graph).unwrap();
+        assert_eq!(
+            output.value(&graph),
+            Some(&Value::String("Internal error: Unavailable".to_string()))
+        );
+
+        // The normal execution flow is not followed.
+        let output = after.output(&graph).unwrap();
+        assert_eq!(output.value(&graph), Some(&Value::String(String::new())));
+    }
+
+    #[tokio::test]
+    async fn test_error_flow_async() {
+        let mut graph = Graph::default();
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let node = graph.add_node(GraphNode::AsyncNode(Arc::new(Flaky {
+            runs: runs.clone(),
+            failures: 2,
+            error: internal_error,
+        })));
+        let handler = CallbackNode::new(&mut graph, |_| Value::Bool(true));
+        node.on_error(&mut graph, handler.0);
+
+        let mut executor = Executor::default();
+        executor.node(node).retry = Some(RetryPolicy {
+            max_attempts: 2,
+            initial_backoff: Duration::from_millis(1),
+            ..Default::default()
+        });
+        executor.run(&mut graph, node).await.unwrap();
+
+        // The error flow is followed once retries are exhausted.
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+
+        let output = handler.output(&graph).unwrap();
+        assert_eq!(output.value(&graph), Some(&Value::Bool(true)));
+    }
+
+    #[tokio::test]
+    async fn test_error_flow_cancelled() {
+        let mut graph = Graph::default();
+        let (node, handler) = failing(&mut graph);
+
+        let executor = Executor::default();
+        executor.cancel.cancel();
+
+        let res = executor.run(&mut graph, node.0).await;
+        assert!(matches!(res, Err(ExecutionStepError::Cancelled)));
+
+        let output = handler.output(&graph).unwrap();
+        assert_eq!(output.value(&graph), Some(&Value::String(String::new())));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_during_backoff() {
+        let mut graph = Graph::default();
+        let node = graph.add_node(GraphNode::SyncNode(Box::new(Flaky {
+            runs: Arc::new(AtomicUsize::new(0)),
+            failures: 1,
+            error: internal_error,
+        })));
+
+        let mut executor = Executor::default();
+        executor.node(node).retry = Some(RetryPolicy {
+            initial_backoff: Duration::from_secs(10),
+            jitter: false,
+            ..Default::default()
+        });
+
+        let cancel = executor.cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            cancel.cancel();
+        });
+
+        let res = tokio::time::timeout(Duration::from_secs(5), executor.run(&mut graph, node))
+            .await
+            .unwrap();
+        assert!(matches!(res, Err(ExecutionStepError::Cancelled)));
+    }
+}
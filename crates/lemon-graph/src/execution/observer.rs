@@ -0,0 +1,213 @@
+use std::time::Duration;
+
+use petgraph::graph::NodeIndex;
+
+use crate::{ExecutionStepError, Value};
+
+/// Event emitted by the [Executor](crate::Executor) while executing a graph.
+///
+/// Each step is given an `id`, unique within the execution, so the events of
+/// concurrent steps, or of a node run more than once, can be told apart.
+/// Retries keep the `id` of their step.
+///
+/// Nodes are indices into the graph being executed, which is a subgraph if
+/// `path` is not empty. The path holds the indices of the subgraph nodes
+/// leading to it, starting from the outer graph.
+#[derive(Debug, Clone, Copy)]
+pub enum ExecutionEvent<'a> {
+    /// A step was taken from the queue, before its inputs are read.
+    StepStarted {
+        id: usize,
+        path: &'a [NodeIndex],
+        node: NodeIndex,
+    },
+    /// The inputs of a node were read from its stores.
+    InputsResolved {
+        id: usize,
+        path: &'a [NodeIndex],
+        node: NodeIndex,
+        inputs: &'a [Value],
+    },
+    /// The outputs of a node were written to its stores.
+    OutputsWritten {
+        id: usize,
+        path: &'a [NodeIndex],
+        node: NodeIndex,
+        outputs: &'a [Value],
+    },
+    /// A node finished running, including any retries.
+    StepFinished {
+        id: usize,
+        path: &'a [NodeIndex],
+        node: NodeIndex,
+        duration: Duration,
+    },
+    /// A node failed, after any retries.
+    /// Sent for every failure, including those followed by error flow edges,
+    /// which continue execution instead of stopping it.
+    StepFailed {
+        id: usize,
+        path: &'a [NodeIndex],
+        node: NodeIndex,
+        error: &'a ExecutionStepError,
+    },
+}
+
+impl ExecutionEvent<'_> {
+    /// Returns the ID of the step the event is about.
+    pub fn id(&self) -> usize {
+        match self {
+            Self::StepStarted { id, .. }
+            | Self::InputsResolved { id, .. }
+            | Self::OutputsWritten { id, .. }
+            | Self::StepFinished { id, .. }
+            | Self::StepFailed { id, .. } => *id,
+        }
+    }
+
+    /// Returns the subgraph nodes leading to the graph of the node, see [ExecutionEvent].
+    pub fn path(&self) -> &[NodeIndex] {
+        match self {
+            Self::StepStarted { path, .. }
+            | Self::InputsResolved { path, .. }
+            | Self::OutputsWritten { path, .. }
+            | Self::StepFinished { path, .. }
+            | Self::StepFailed { path, .. } => path,
+        }
+    }
+
+    /// Returns the node the event is about.
+    pub fn node(&self) -> NodeIndex {
+        match self {
+            Self::StepStarted { node, .. }
+            | Self::InputsResolved { node, .. }
+            | Self::OutputsWritten { node, .. }
+            | Self::StepFinished { node, .. }
+            | Self::StepFailed { node, .. } => *node,
+        }
+    }
+}
+
+/// Receives [ExecutionEvent]s from an [Executor](crate::Executor).
+///
+/// Events are delivered in order, from the task driving the execution,
+/// so observers should return quickly.
+pub trait ExecutionObserver: Send + Sync {
+    fn on_event(&self, event: &ExecutionEvent);
+}
+
+impl<F: Fn(&ExecutionEvent) + Send + Sync> ExecutionObserver for F {
+    fn on_event(&self, event: &ExecutionEvent) {
+        self(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::{
+        nodes::{BranchNode, CallbackNode, Node},
+        Executor, Graph,
+    };
+
+    use super::*;
+
+    /// Simplified event that can be stored and compared.
+    #[derive(Debug, PartialEq)]
+    enum Recorded {
+        Started(NodeIndex),
+        Inputs(NodeIndex, Vec<Value>),
+        Outputs(NodeIndex, Vec<Value>),
+        Finished(NodeIndex),
+        Failed(NodeIndex),
+    }
+
+    fn record(executor: &mut Executor) -> Arc<Mutex<Vec<Recorded>>> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        let recorded = events.clone();
+        executor
+            .observers
+            .push(Arc::new(move |event: &ExecutionEvent| {
+                let event = match *event {
+                    ExecutionEvent::StepStarted { node, .. } => Recorded::Started(node),
+                    ExecutionEvent::InputsResolved { node, inputs, .. } => {
+                        Recorded::Inputs(node, inputs.to_vec())
+                    }
+                    ExecutionEvent::OutputsWritten { node, outputs, .. } => {
+                        Recorded::Outputs(node, outputs.to_vec())
+                    }
+                    ExecutionEvent::StepFinished { node, .. } => Recorded::Finished(node),
+                    ExecutionEvent::StepFailed { node, .. } => Recorded::Failed(node),
+                };
+                recorded.lock().unwrap().push(event);
+            }));
+
+        events
+    }
+
+    #[tokio::test]
+    async fn test_observer() {
+        let mut graph = Graph::default();
+
+        let node = CallbackNode::new(&mut graph, |_| Value::String("world".to_string()));
+        let input = node.input(&graph).unwrap();
+        input.set_value(&mut graph, Value::String("hello".to_string()));
+
+        let mut executor = Executor::default();
+        let events = record(&mut executor);
+
+        executor.run(&mut graph, node.0).await.unwrap();
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                Recorded::Started(node.0),
+                Recorded::Inputs(node.0, vec![Value::String("hello".to_string())]),
+                Recorded::Outputs(node.0, vec![Value::String("world".to_string())]),
+                Recorded::Finished(node.0),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_observer_failed() {
+        let mut graph = Graph::default();
+
+        let branch = BranchNode::new(&mut graph);
+        let condition = branch.condition(&graph).unwrap();
+        condition.set_value(&mut graph, Value::String("true".to_string()));
+
+        let mut executor = Executor::default();
+        let events = record(&mut executor);
+
+        assert!(executor.run(&mut graph, branch.0).await.is_err());
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.last(), Some(&Recorded::Failed(branch.0)));
+    }
+
+    #[tokio::test]
+    async fn test_observer_recovered() {
+        let mut graph = Graph::default();
+
+        let node = CallbackNode::try_new(&mut graph, |_| Err::<Value, _>("Unavailable"));
+        let handler = CallbackNode::new(&mut graph, |value| value);
+        node.on_error(&mut graph, handler.0);
+
+        let mut executor = Executor::default();
+        let events = record(&mut executor);
+
+        executor.run(&mut graph, node.0).await.unwrap();
+
+        // The failure is reported, then execution continues through the error flow.
+        let events = events.lock().unwrap();
+        let failed = events
+            .iter()
+            .position(|event| *event == Recorded::Failed(node.0))
+            .unwrap();
+        assert_eq!(events[failed + 1], Recorded::Started(handler.0));
+        assert_eq!(events.last(), Some(&Recorded::Finished(handler.0)));
+    }
+}
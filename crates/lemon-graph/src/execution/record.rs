@@ -0,0 +1,486 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use petgraph::graph::NodeIndex;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{ExecutionEvent, ExecutionObserver, Graph, GraphNode, Value};
+
+/// Inputs and outputs of a single step, captured by a [Recorder].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TracedStep {
+    /// Indices of the subgraph nodes the node was run in, starting from the
+    /// outer graph. Empty for nodes of the outer graph.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub path: Vec<usize>,
+    /// Index of the node that was run, within its graph.
+    pub node: usize,
+    pub inputs: Vec<Value>,
+    /// Outputs of the node, empty if it failed.
+    pub outputs: Vec<Value>,
+    /// Error message, if the step failed.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub error: Option<String>,
+}
+
+/// Every step of an execution, in the order the steps finished or failed.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Trace {
+    pub steps: Vec<TracedStep>,
+}
+
+#[cfg(feature = "json")]
+#[derive(Debug, thiserror::Error)]
+pub enum TraceFileError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+#[cfg(feature = "json")]
+impl Trace {
+    /// Saves the trace to a JSON file.
+    /// Fails if any recorded value is a [Value::Stream], as streams cannot be serialized.
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), TraceFileError> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// Loads a trace from a JSON file.
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, TraceFileError> {
+        let json = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+}
+
+/// [ExecutionObserver] that captures the inputs and outputs of every step
+/// into a [Trace], including steps that failed.
+#[derive(Default)]
+pub struct Recorder {
+    /// Inputs of steps that have not finished yet, by step ID.
+    inputs: Mutex<HashMap<usize, Vec<Value>>>,
+    trace: Mutex<Trace>,
+}
+
+impl Recorder {
+    /// Returns the steps recorded so far.
+    pub fn trace(&self) -> Trace {
+        self.trace.lock().unwrap().clone()
+    }
+}
+
+impl Recorder {
+    fn push(&self, event: &ExecutionEvent, outputs: Vec<Value>, error: Option<String>) {
+        let inputs = self
+            .inputs
+            .lock()
+            .unwrap()
+            .remove(&event.id())
+            .unwrap_or_default();
+
+        self.trace.lock().unwrap().steps.push(TracedStep {
+            path: event.path().iter().map(|node| node.index()).collect(),
+            node: event.node().index(),
+            inputs,
+            outputs,
+            error,
+        });
+    }
+}
+
+impl ExecutionObserver for Recorder {
+    fn on_event(&self, event: &ExecutionEvent) {
+        match *event {
+            ExecutionEvent::InputsResolved { id, inputs, .. } => {
+                self.inputs.lock().unwrap().insert(id, inputs.to_vec());
+            }
+            ExecutionEvent::OutputsWritten { outputs, .. } => {
+                self.push(event, outputs.to_vec(), None);
+            }
+            ExecutionEvent::StepFailed { error, .. } => {
+                self.push(event, Vec::new(), Some(error.to_string()));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Recorded outputs to use in place of running nodes.
+///
+/// Each time a replayed node is reached, its next recorded outputs are
+/// written instead of running it, so the rest of the graph sees the same
+/// values as the recorded execution.
+/// Flow nodes are always run, as they decide which steps follow, and failed
+/// steps are not replayed.
+///
+/// Nodes are matched by their index and the path of subgraph nodes leading
+/// to them, see [TracedStep::path].
+/// Clones share the same recorded outputs, so subgraphs can replay their nodes.
+#[derive(Debug, Clone, Default)]
+pub struct Replay {
+    outputs: Arc<Mutex<HashMap<ReplayKey, VecDeque<Vec<Value>>>>>,
+}
+
+/// Path to the graph of a node, and its index.
+type ReplayKey = (Vec<NodeIndex>, NodeIndex);
+
+impl Replay {
+    /// Replays the nodes of the trace accepted by the filter, given their path
+    /// and index.
+    pub fn new(trace: &Trace, filter: impl Fn(&[NodeIndex], NodeIndex) -> bool) -> Self {
+        let mut outputs = HashMap::<_, VecDeque<_>>::new();
+
+        for step in &trace.steps {
+            let path = step
+                .path
+                .iter()
+                .copied()
+                .map(NodeIndex::new)
+                .collect::<Vec<_>>();
+            let node = NodeIndex::new(step.node);
+
+            if step.error.is_none() && filter(&path, node) {
+                outputs
+                    .entry((path, node))
+                    .or_default()
+                    .push_back(step.outputs.clone());
+            }
+        }
+
+        Self {
+            outputs: Arc::new(Mutex::new(outputs)),
+        }
+    }
+
+    /// Replays the nodes of the trace with the given [NodeData](crate::NodeData) name,
+    /// such as every LLM node in the graph.
+    /// Only nodes of the given graph are replayed, not those of its subgraphs.
+    pub fn named(trace: &Trace, graph: &Graph, name: &str) -> Self {
+        Self::new(trace, |path, node| {
+            path.is_empty()
+                && graph
+                    .node_weight(node)
+                    .and_then(GraphNode::data)
+                    .is_some_and(|data| data.name == name)
+        })
+    }
+
+    /// Returns whether the node is replayed, given the path to its graph.
+    pub fn contains(&self, path: &[NodeIndex], node: NodeIndex) -> bool {
+        self.outputs
+            .lock()
+            .unwrap()
+            .contains_key(&(path.to_vec(), node))
+    }
+
+    /// Takes the next recorded outputs of the node, given the path to its graph.
+    pub fn next(&self, path: &[NodeIndex], node: NodeIndex) -> Option<Vec<Value>> {
+        self.outputs
+            .lock()
+            .unwrap()
+            .get_mut(&(path.to_vec(), node))?
+            .pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use std::time::Duration;
+
+    use crate::{
+        async_trait,
+        nodes::{
+            AsyncNode, CallbackNode, Node, NodeError, Store, Subgraph, SubgraphNode, SyncNode,
+        },
+        ExecutionStepError, Executor, GraphEdge, NodeData,
+    };
+
+    use super::*;
+
+    /// Returns a different value each time it is run.
+    struct Counter(Arc<AtomicUsize>);
+
+    impl Counter {
+        const NAME: &'static str = "test.counter";
+    }
+
+    impl SyncNode for Counter {
+        fn run(&self, _inputs: Vec<Value>) -> Result<Vec<Value>, NodeError> {
+            let count = self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(vec![Value::USize(count)])
+        }
+
+        fn data(&self) -> Option<NodeData> {
+            Some(NodeData::new(Self::NAME))
+        }
+    }
+
+    /// Returns its inputs after a short delay.
+    struct Slow;
+
+    #[async_trait]
+    impl AsyncNode for Slow {
+        async fn run(&self, inputs: Vec<Value>) -> Result<Vec<Value>, NodeError> {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            Ok(inputs)
+        }
+    }
+
+    /// Counter -> callback, which receives the count.
+    fn counter_graph(count: Arc<AtomicUsize>) -> (Graph, NodeIndex, CallbackNode) {
+        let mut graph = Graph::default();
+
+        let counter = graph.add_node(GraphNode::SyncNode(Box::new(Counter(count))));
+        let output = graph.add_node(GraphNode::Store(Value::USize(0)));
+        graph.add_edge(counter, output, GraphEdge::DataMap(0));
+
+        let callback = CallbackNode::new(&mut graph, |value| value);
+        callback.run_after(&mut graph, counter);
+        let input = callback.input(&graph).unwrap();
+        input.set_input(&mut graph, Some(Store(output))).unwrap();
+
+        (graph, counter, callback)
+    }
+
+    #[tokio::test]
+    async fn test_record() {
+        let (mut graph, counter, callback) = counter_graph(Arc::new(AtomicUsize::new(5)));
+
+        let recorder = Arc::new(Recorder::default());
+        let executor = Executor {
+            observers: vec![recorder.clone()],
+            ..Default::default()
+        };
+        executor.run(&mut graph, counter).await.unwrap();
+
+        assert_eq!(
+            recorder.trace().steps,
+            vec![
+                TracedStep {
+                    path: Vec::new(),
+                    node: counter.index(),
+                    inputs: Vec::new(),
+                    outputs: vec![Value::USize(5)],
+                    error: None,
+                },
+                TracedStep {
+                    path: Vec::new(),
+                    node: callback.0.index(),
+                    inputs: vec![Value::USize(5)],
+                    outputs: vec![Value::USize(5)],
+                    error: None,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replay() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let (mut graph, counter, _) = counter_graph(count.clone());
+
+        let recorder = Arc::new(Recorder::default());
+        let executor = Executor {
+            observers: vec![recorder.clone()],
+            ..Default::default()
+        };
+        executor.run(&mut graph, counter).await.unwrap();
+
+        let trace = recorder.trace();
+        let recorder = Arc::new(Recorder::default());
+        let executor = Executor {
+            observers: vec![recorder.clone()],
+            replay: Some(Replay::named(&trace, &graph, Counter::NAME)),
+            ..Default::default()
+        };
+        executor.run(&mut graph, counter).await.unwrap();
+
+        // The counter was not run again, and the callback saw the recorded value.
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        assert_eq!(recorder.trace(), trace);
+
+        // Replaying past the end of the recording fails.
+        let res = executor.run(&mut graph, counter).await;
+        assert!(matches!(
+            res,
+            Err(ExecutionStepError::MissingReplay(node)) if node == counter
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_replay_subgraph() {
+        let outer = Arc::new(AtomicUsize::new(0));
+        let inner = Arc::new(AtomicUsize::new(10));
+
+        // Counter -> callback -> subgraph, holding another counter -> callback.
+        let (mut graph, counter, callback) = counter_graph(outer.clone());
+        let (inner_graph, inner_counter, inner_callback) = counter_graph(inner.clone());
+        let subgraph = SubgraphNode::new(
+            &mut graph,
+            Subgraph::new(inner_graph, inner_counter, inner_callback.0),
+        );
+        subgraph.run_after(&mut graph, callback.0);
+
+        let recorder = Arc::new(Recorder::default());
+        let executor = Executor {
+            observers: vec![recorder.clone()],
+            ..Default::default()
+        };
+        executor.run(&mut graph, counter).await.unwrap();
+
+        // Both counters have the same index, but are told apart by their path.
+        let counters = |trace: &Trace| {
+            trace
+                .steps
+                .iter()
+                .filter(|step| step.node == counter.index())
+                .map(|step| (step.path.clone(), step.outputs.clone()))
+                .collect::<Vec<_>>()
+        };
+        let trace = recorder.trace();
+        assert_eq!(inner_counter, counter);
+        assert_eq!(
+            counters(&trace),
+            vec![
+                (Vec::new(), vec![Value::USize(0)]),
+                (vec![subgraph.0.index()], vec![Value::USize(10)]),
+            ]
+        );
+
+        // Only the outer counter is replayed.
+        let recorder = Arc::new(Recorder::default());
+        let executor = Executor {
+            observers: vec![recorder.clone()],
+            replay: Some(Replay::new(&trace, |path, node| {
+                path.is_empty() && node == counter
+            })),
+            ..Default::default()
+        };
+        executor.run(&mut graph, counter).await.unwrap();
+
+        assert_eq!(outer.load(Ordering::SeqCst), 1);
+        assert_eq!(inner.load(Ordering::SeqCst), 12);
+        assert_eq!(
+            counters(&recorder.trace()),
+            vec![
+                (Vec::new(), vec![Value::USize(0)]),
+                (vec![subgraph.0.index()], vec![Value::USize(11)]),
+            ]
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_trace_file() {
+        let trace = Trace {
+            steps: vec![TracedStep {
+                path: vec![0],
+                node: 1,
+                inputs: vec![Value::String("prompt".to_string())],
+                outputs: vec![Value::String("answer".to_string())],
+                error: None,
+            }],
+        };
+
+        let path = std::env::temp_dir().join(format!("lemon-trace-{}.json", std::process::id()));
+        trace.save(&path).unwrap();
+        let loaded = Trace::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, trace);
+
+        // Streams cannot be saved.
+        let trace = Trace {
+            steps: vec![TracedStep {
+                path: vec![0],
+                node: 1,
+                inputs: Vec::new(),
+                outputs: vec![Value::Stream(crate::ValueStream::from_chunks(Vec::new()))],
+                error: None,
+            }],
+        };
+        assert!(matches!(trace.save(&path), Err(TraceFileError::Json(_))));
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_record_concurrent() {
+        let mut graph = Graph::default();
+
+        // Start runs the counter twice, and each run starts the slow node,
+        // so two runs of it overlap with different inputs.
+        let start = CallbackNode::new(&mut graph, |value| value);
+        let counter = graph.add_node(GraphNode::SyncNode(Box::new(Counter(Default::default()))));
+        start.run_before(&mut graph, counter);
+        start.run_before(&mut graph, counter);
+
+        let count = graph.add_node(GraphNode::Store(Value::Null));
+        graph.add_edge(counter, count, GraphEdge::DataMap(0));
+
+        let slow = graph.add_node(GraphNode::AsyncNode(Arc::new(Slow)));
+        slow.run_after(&mut graph, counter);
+        graph.add_edge(count, slow, GraphEdge::DataMap(0));
+        let output = graph.add_node(GraphNode::Store(Value::Null));
+        graph.add_edge(slow, output, GraphEdge::DataMap(0));
+
+        let failing = CallbackNode::try_new(&mut graph, |_| Err::<Value, _>("Oops"));
+        let handler = CallbackNode::new(&mut graph, |value| value);
+        failing.run_after(&mut graph, start.0);
+        failing.on_error(&mut graph, handler.0);
+
+        let recorder = Arc::new(Recorder::default());
+        let executor = Executor {
+            concurrency: 4,
+            observers: vec![recorder.clone()],
+            ..Default::default()
+        };
+        executor.run(&mut graph, start.0).await.unwrap();
+
+        let steps = recorder.trace().steps;
+
+        // Each run of the slow node is paired with its own inputs.
+        let mut runs = steps
+            .iter()
+            .filter(|step| step.node == slow.index())
+            .map(|step| (step.inputs.clone(), step.outputs.clone()))
+            .collect::<Vec<_>>();
+        runs.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        assert_eq!(
+            runs,
+            vec![
+                (vec![Value::USize(0)], vec![Value::USize(0)]),
+                (vec![Value::USize(1)], vec![Value::USize(1)]),
+            ]
+        );
+
+        let failed = steps
+            .iter()
+            .find(|step| step.node == failing.0.index())
+            .unwrap();
+        assert!(failed.outputs.is_empty());
+        assert_eq!(failed.error.as_deref(), Some("Internal error: Oops"));
+
+        // Failed steps are not replayed.
+        let replay = Replay::new(&recorder.trace(), |_, _| true);
+        assert!(!replay.contains(&[], failing.0));
+    }
+}
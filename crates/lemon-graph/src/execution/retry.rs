@@ -0,0 +1,137 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::nodes::NodeError;
+
+/// Policy for retrying a node that fails.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, including the first.
+    pub max_attempts: usize,
+    /// Delay before the first retry.
+    pub initial_backoff: Duration,
+    /// Maximum delay between attempts.
+    pub max_backoff: Duration,
+    /// Factor the delay is multiplied by after each retry.
+    pub multiplier: f64,
+    /// Whether to randomize each delay, between half and all of its value.
+    pub jitter: bool,
+    /// Returns whether an error can be retried.
+    pub retryable: fn(&NodeError) -> bool,
+    /// Whether to retry attempts that time out.
+    pub timeouts: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: true,
+            retryable: |error| matches!(error, NodeError::InternalError(_)),
+            timeouts: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns whether another attempt should be made, after the given
+    /// number of attempts failed with the given error.
+    pub fn should_retry(&self, attempts: usize, error: &NodeError) -> bool {
+        attempts < self.max_attempts && (self.retryable)(error)
+    }
+
+    /// Returns whether another attempt should be made, after the given
+    /// number of attempts failed by timing out.
+    pub fn should_retry_timeout(&self, attempts: usize) -> bool {
+        attempts < self.max_attempts && self.timeouts
+    }
+
+    /// Returns the delay before the given retry, starting from 1.
+    pub fn backoff(&self, retry: usize) -> Duration {
+        let exponent = retry.saturating_sub(1).min(i32::MAX as usize) as i32;
+        let backoff = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        let backoff = backoff.min(self.max_backoff.as_secs_f64());
+
+        let backoff = if self.jitter {
+            backoff * rand::thread_rng().gen_range(0.5..=1.0)
+        } else {
+            backoff
+        };
+
+        // Guard against a negative or NaN multiplier, and overflow.
+        Duration::try_from_secs_f64(backoff.max(0.0)).unwrap_or(self.max_backoff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(300),
+            jitter: false,
+            ..Default::default()
+        };
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(300));
+        assert_eq!(policy.backoff(100), Duration::from_millis(300));
+    }
+
+    #[test]
+    fn test_jitter() {
+        let policy = RetryPolicy::default();
+
+        for _ in 0..100 {
+            let backoff = policy.backoff(1);
+            assert!(backoff >= Duration::from_millis(50));
+            assert!(backoff <= Duration::from_millis(100));
+        }
+    }
+
+    #[test]
+    fn test_should_retry() {
+        let policy = RetryPolicy::default();
+        let internal = NodeError::InternalError("Failed".to_string());
+
+        assert!(policy.should_retry(1, &internal));
+        assert!(!policy.should_retry(3, &internal));
+        assert!(!policy.should_retry(1, &NodeError::MissingInput(0)));
+
+        assert!(policy.should_retry_timeout(1));
+        assert!(!policy.should_retry_timeout(3));
+
+        let policy = RetryPolicy {
+            timeouts: false,
+            ..Default::default()
+        };
+        assert!(!policy.should_retry_timeout(1));
+    }
+
+    #[test]
+    fn test_invalid_multiplier() {
+        for multiplier in [-1.0, f64::NAN, f64::INFINITY] {
+            let policy = RetryPolicy {
+                multiplier,
+                ..Default::default()
+            };
+            assert!(policy.backoff(2) <= policy.max_backoff);
+        }
+
+        let policy = RetryPolicy {
+            max_backoff: Duration::MAX,
+            multiplier: f64::INFINITY,
+            jitter: false,
+            ..Default::default()
+        };
+        assert_eq!(policy.backoff(2), Duration::MAX);
+    }
+}
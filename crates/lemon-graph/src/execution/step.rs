@@ -1,8 +1,15 @@
+use std::hash::{Hash, Hasher};
+
 use petgraph::{graph::NodeIndex, visit::EdgeRef, Direction};
 use thiserror::Error;
 
-use crate::{nodes::NodeError, Graph, GraphEdge, GraphNode};
+use super::cache::StableHasher;
+use crate::{
+    nodes::{Flow, NodeError},
+    Diagnostic, ExecutionContext, Graph, GraphEdge, GraphNode, Limit, Value,
+};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ExecutionStep(pub NodeIndex);
 
 #[derive(Debug, Error)]
@@ -11,16 +18,143 @@ pub enum ExecutionStepError {
     NoWeight,
     #[error("Invalid weight")]
     InvalidWeight,
+    #[error("Invalid graph: {0:?}")]
+    InvalidGraph(Vec<Diagnostic>),
+    #[error("Execution was cancelled")]
+    Cancelled,
+    #[error("Node {0:?} timed out")]
+    Timeout(NodeIndex),
+    #[error("No recorded outputs left to replay for node {0:?}")]
+    MissingReplay(NodeIndex),
+    #[error("Exceeded {limit} at node {node:?}")]
+    LimitExceeded { limit: Limit, node: NodeIndex },
     #[error(transparent)]
     NodeError(#[from] NodeError),
 }
 
 impl ExecutionStep {
+    /// Runs the node with a new [ExecutionContext], and returns the steps that follow it.
     pub async fn execute<'a>(
         &self,
         graph: &'a mut Graph,
     ) -> Result<impl Iterator<Item = ExecutionStep> + 'a, ExecutionStepError> {
-        // Read inputs
+        self.execute_with_context(graph, &ExecutionContext::default())
+            .await
+    }
+
+    /// Runs the node, and returns the steps that follow it.
+    ///
+    /// If the node fails and has [GraphEdge::ErrorFlow] edges, the error is
+    /// handled and the error flows are returned instead.
+    pub async fn execute_with_context<'a>(
+        &self,
+        graph: &'a mut Graph,
+        ctx: &ExecutionContext,
+    ) -> Result<impl Iterator<Item = ExecutionStep> + 'a, ExecutionStepError> {
+        let next = match self.run(graph, ctx).await {
+            Ok(flow) => self.next_steps(graph, flow).collect::<Vec<_>>(),
+            Err(error) => self.handle_error(graph, &error).ok_or(error)?,
+        };
+
+        Ok(next.into_iter())
+    }
+
+    /// Runs the node, writing its outputs.
+    async fn run(
+        &self,
+        graph: &mut Graph,
+        ctx: &ExecutionContext,
+    ) -> Result<Flow, ExecutionStepError> {
+        let inputs = self.read_inputs(graph)?;
+
+        // Execute node
+        let node = graph
+            .node_weight(self.0)
+            .ok_or(ExecutionStepError::NoWeight)?;
+
+        let (res, flow) = match node {
+            GraphNode::AsyncNode(node) => {
+                (node.run_with_context(ctx, inputs).await?, Flow::Continue)
+            }
+            GraphNode::SyncNode(node) => (node.run_with_context(ctx, inputs)?, Flow::Continue),
+            GraphNode::FlowNode(node) => node.run_with_context(ctx, inputs)?,
+            _ => return Err(ExecutionStepError::InvalidWeight),
+        };
+
+        self.write_outputs(graph, res);
+
+        Ok(flow)
+    }
+
+    /// Handles an error from running the node, if it has [GraphEdge::ErrorFlow] edges.
+    ///
+    /// The error message is written to any [GraphEdge::ErrorMap] stores, and the
+    /// steps of the error flows are returned.
+    /// Returns `None` if the node has no error flows, or if the error stops
+    /// the whole execution, such as when it was cancelled.
+    pub fn handle_error(
+        &self,
+        graph: &mut Graph,
+        error: &ExecutionStepError,
+    ) -> Option<Vec<ExecutionStep>> {
+        if matches!(
+            error,
+            ExecutionStepError::Cancelled
+                | ExecutionStepError::InvalidGraph(_)
+                | ExecutionStepError::LimitExceeded { .. }
+        ) {
+            return None;
+        }
+
+        let mut next = Vec::new();
+        let mut stores = Vec::new();
+
+        for edge in graph.edges_directed(self.0, Direction::Outgoing) {
+            match edge.weight() {
+                GraphEdge::ErrorFlow => next.push(ExecutionStep(edge.target())),
+                GraphEdge::ErrorMap => stores.push(edge.target()),
+                _ => {}
+            }
+        }
+
+        if next.is_empty() {
+            return None;
+        }
+
+        for store in stores {
+            graph[store] = GraphNode::Store(Value::String(error.to_string()));
+        }
+
+        self.arrive(graph, &next);
+
+        Some(next)
+    }
+
+    /// Returns the key used to cache the outputs of the node, given its inputs.
+    ///
+    /// Nodes are identified by their [NodeData](crate::NodeData), so the key
+    /// stays the same when the graph is saved and loaded, or rewired.
+    /// Returns `None` if the node has no [NodeData](crate::NodeData), as nothing
+    /// tells it apart from other nodes, or if any input is a [Value::Stream],
+    /// as its contents are not known.
+    pub fn cache_key(&self, graph: &Graph, inputs: &[Value]) -> Option<u64> {
+        if inputs.iter().any(|input| matches!(input, Value::Stream(_))) {
+            return None;
+        }
+
+        let data = graph.node_weight(self.0).and_then(GraphNode::data)?;
+
+        let mut hasher = StableHasher::new();
+        data.name.hash(&mut hasher);
+        data.config.hash(&mut hasher);
+        inputs.hash(&mut hasher);
+
+        Some(hasher.finish())
+    }
+
+    /// Reads the inputs of the node, sorted by data index.
+    /// Input stores are first updated from any incoming [GraphEdge::DataFlow] edges.
+    pub fn read_inputs(&self, graph: &mut Graph) -> Result<Vec<Value>, ExecutionStepError> {
         let inputs = graph
             .edges_directed(self.0, Direction::Incoming)
             .filter_map(|edge| match edge.weight() {
@@ -70,21 +204,12 @@ impl ExecutionStep {
 
         inputs.sort_by_key(|(idx, _)| *idx);
 
-        let inputs = inputs.into_iter().map(|(_, value)| value).collect();
-
-        // Execute node
-        let node = graph
-            .node_weight(self.0)
-            .ok_or(ExecutionStepError::NoWeight)?;
-
-        let res = match node {
-            GraphNode::AsyncNode(node) => node.run(inputs).await?,
-            GraphNode::SyncNode(node) => node.run(inputs)?,
-            _ => return Err(ExecutionStepError::InvalidWeight),
-        };
+        Ok(inputs.into_iter().map(|(_, value)| value).collect())
+    }
 
-        // Write outputs
-        let outputs = graph
+    /// Writes the outputs of the node to its output stores.
+    pub fn write_outputs(&self, graph: &mut Graph, outputs: Vec<Value>) {
+        let stores = graph
             .edges_directed(self.0, Direction::Outgoing)
             .filter_map(|edge| match edge.weight() {
                 GraphEdge::DataMap(data_idx) => Some((edge.target(), *data_idx)),
@@ -92,30 +217,62 @@ impl ExecutionStep {
             })
             .collect::<Vec<_>>();
 
-        for (i, value) in res.into_iter().enumerate() {
-            let (store_idx, _) = match outputs.iter().find(|(_, idx)| *idx == i) {
+        for (i, value) in outputs.into_iter().enumerate() {
+            let (store_idx, _) = match stores.iter().find(|(_, idx)| *idx == i) {
                 Some(output) => output,
                 None => continue,
             };
 
             graph[*store_idx] = GraphNode::Store(value);
         }
+    }
 
-        // Get next steps
-        Ok(graph
+    /// Returns the steps that follow this one, given the resulting [Flow].
+    ///
+    /// For [Flow::Loop], this step is returned first, followed by the loop body.
+    /// When executed from a stack, the body then completes before this step runs again.
+    pub fn next_steps<'a>(
+        &self,
+        graph: &'a Graph,
+        flow: Flow,
+    ) -> impl Iterator<Item = ExecutionStep> + 'a {
+        let repeat = std::iter::once(*self).filter(move |_| matches!(flow, Flow::Loop(_)));
+
+        let next = graph
             .edges_directed(self.0, Direction::Outgoing)
-            .filter_map(|edge| match edge.weight() {
-                GraphEdge::ExecutionFlow => Some(ExecutionStep(edge.target())),
+            .filter_map(move |edge| match (edge.weight(), flow) {
+                (GraphEdge::ExecutionFlow(_), Flow::Continue) => Some(ExecutionStep(edge.target())),
+                (GraphEdge::ExecutionFlow(idx), Flow::Output(output) | Flow::Loop(output))
+                    if *idx == output =>
+                {
+                    Some(ExecutionStep(edge.target()))
+                }
                 _ => None,
-            }))
+            })
+            .collect::<Vec<_>>();
+
+        self.arrive(graph, &next);
+
+        repeat.chain(next)
+    }
+
+    /// Notifies flow nodes that execution is about to reach them from this step.
+    fn arrive(&self, graph: &Graph, next: &[ExecutionStep]) {
+        for step in next {
+            if let Some(GraphNode::FlowNode(node)) = graph.node_weight(step.0) {
+                node.arrive(graph, step.0, self.0);
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use crate::{
-        nodes::{AsyncNode, SyncNode},
-        Value,
+        async_trait,
+        nodes::{AsyncNode, CallbackNode, Node, SyncNode},
     };
 
     use super::*;
@@ -130,12 +287,10 @@ mod tests {
 
     struct TestAsync;
 
+    #[async_trait]
     impl AsyncNode for TestAsync {
-        fn run(
-            &self,
-            inputs: Vec<Value>,
-        ) -> Box<dyn std::future::Future<Output = Result<Vec<Value>, NodeError>> + Unpin> {
-            Box::new(Box::pin(async move { Ok(inputs) }))
+        async fn run(&self, inputs: Vec<Value>) -> Result<Vec<Value>, NodeError> {
+            Ok(inputs)
         }
     }
 
@@ -166,7 +321,7 @@ mod tests {
         let mut graph = Graph::default();
 
         let input = graph.add_node(GraphNode::Store(Value::String("Hello, world!".to_string())));
-        let node = graph.add_node(GraphNode::AsyncNode(Box::new(TestAsync)));
+        let node = graph.add_node(GraphNode::AsyncNode(Arc::new(TestAsync)));
         let output = graph.add_node(GraphNode::Store(Value::String(Default::default())));
         graph.add_edge(input, node, GraphEdge::DataMap(0));
         graph.add_edge(node, output, GraphEdge::DataMap(0));
@@ -182,4 +337,26 @@ mod tests {
         };
         assert_eq!(output_value, &Value::String("Hello, world!".to_string()));
     }
+
+    #[tokio::test]
+    async fn test_error_flow() {
+        let mut graph = Graph::default();
+
+        let node = CallbackNode::try_new(&mut graph, |_| Err::<Value, _>("Oops"));
+        let handler = CallbackNode::new(&mut graph, |value| value);
+
+        let step = ExecutionStep(node.0);
+        let res = step.execute(&mut graph).await.map(Iterator::count);
+        assert!(matches!(res, Err(ExecutionStepError::NodeError(_))));
+
+        node.on_error(&mut graph, handler.0);
+        let error = node.error_store(&mut graph);
+
+        let next_steps = step.execute(&mut graph).await.unwrap().collect::<Vec<_>>();
+        assert_eq!(next_steps, vec![ExecutionStep(handler.0)]);
+        assert_eq!(
+            error.value(&graph),
+            Some(&Value::String("Internal error: Oops".to_string()))
+        );
+    }
 }
@@ -0,0 +1,195 @@
+use std::fmt::Write;
+
+use petgraph::{graph::EdgeReference, visit::EdgeRef};
+
+use crate::{Graph, GraphEdge, GraphNode, Value};
+
+/// Maximum number of characters of a store value shown in a label.
+const MAX_VALUE_LEN: usize = 40;
+
+/// Renders the graph in the Graphviz DOT format.
+///
+/// Executable nodes are boxes labelled with their type, and stores are
+/// ellipses labelled with their value.
+/// Execution flow edges are bold, data flow edges are dashed, and data map
+/// edges are dotted and labelled with their port.
+/// Error flow and error map edges are red.
+pub fn to_dot(graph: &Graph) -> String {
+    let mut dot = String::from("digraph {\n");
+
+    for idx in graph.node_indices() {
+        let shape = match &graph[idx] {
+            GraphNode::Store(_) => "ellipse",
+            _ => "box",
+        };
+        let label = node_label(&graph[idx])
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"");
+
+        let _ = writeln!(
+            dot,
+            "    {} [label=\"{}\", shape={}]",
+            idx.index(),
+            label,
+            shape
+        );
+    }
+
+    for edge in graph.edge_references() {
+        let (source, target) = (edge.source().index(), edge.target().index());
+
+        let attrs = match edge.weight() {
+            GraphEdge::ExecutionFlow(0) => "style=bold".to_string(),
+            GraphEdge::ExecutionFlow(output) => format!("style=bold, label=\"{}\"", output),
+            GraphEdge::DataFlow => "style=dashed".to_string(),
+            GraphEdge::DataMap(_) => {
+                format!("style=dotted, label=\"{}\"", port_label(graph, edge))
+            }
+            GraphEdge::ErrorFlow => "style=bold, color=red, label=\"error\"".to_string(),
+            GraphEdge::ErrorMap => "style=dotted, color=red, label=\"error\"".to_string(),
+        };
+
+        let _ = writeln!(dot, "    {} -> {} [{}]", source, target, attrs);
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+/// Renders the graph as a Mermaid flowchart.
+///
+/// Executable nodes are rectangles labelled with their type, and stores are
+/// rounded and labelled with their value.
+/// Execution flow edges are thick, data flow edges are dotted, and data map
+/// edges are plain and labelled with their port.
+/// Error flow and error map edges are labelled `error`.
+pub fn to_mermaid(graph: &Graph) -> String {
+    let mut mermaid = String::from("flowchart TD\n");
+
+    for idx in graph.node_indices() {
+        let label = node_label(&graph[idx]).replace('"', "#quot;");
+
+        let _ = match &graph[idx] {
+            GraphNode::Store(_) => writeln!(mermaid, "    n{}([\"{}\"])", idx.index(), label),
+            _ => writeln!(mermaid, "    n{}[\"{}\"]", idx.index(), label),
+        };
+    }
+
+    for edge in graph.edge_references() {
+        let (source, target) = (edge.source().index(), edge.target().index());
+
+        let arrow = match edge.weight() {
+            GraphEdge::ExecutionFlow(0) => "==>".to_string(),
+            GraphEdge::ExecutionFlow(output) => format!("==>|{}|", output),
+            GraphEdge::DataFlow => "-.->".to_string(),
+            GraphEdge::DataMap(_) => format!("-->|{}|", port_label(graph, edge)),
+            GraphEdge::ErrorFlow => "==>|error|".to_string(),
+            GraphEdge::ErrorMap => "-->|error|".to_string(),
+        };
+
+        let _ = writeln!(mermaid, "    n{} {} n{}", source, arrow, target);
+    }
+
+    mermaid
+}
+
+/// Returns the type of an executable node, or the value of a store.
+fn node_label(node: &GraphNode) -> String {
+    let value = match node {
+        GraphNode::Store(value) => value,
+        node => {
+            return match node.data() {
+                Some(data) => data.name,
+                None => match node {
+                    GraphNode::AsyncNode(_) => "AsyncNode",
+                    GraphNode::SyncNode(_) => "SyncNode",
+                    _ => "FlowNode",
+                }
+                .to_string(),
+            };
+        }
+    };
+
+    let label = match value {
+        Value::String(value) => format!("{:?}", value),
+        value => value.to_string(),
+    };
+
+    if label.chars().count() > MAX_VALUE_LEN {
+        let label = label.chars().take(MAX_VALUE_LEN).collect::<String>();
+        format!("{}...", label)
+    } else {
+        label
+    }
+}
+
+/// Returns the name of the port a data map edge connects to, or its index.
+fn port_label(graph: &Graph, edge: EdgeReference<GraphEdge>) -> String {
+    let GraphEdge::DataMap(index) = *edge.weight() else {
+        return String::new();
+    };
+
+    let ports = match &graph[edge.target()] {
+        GraphNode::Store(_) => graph[edge.source()].ports().outputs,
+        node => node.ports().inputs,
+    };
+
+    match ports.get(index) {
+        Some(port) if !port.name.is_empty() => port.name.to_string(),
+        _ => index.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::nodes::{BranchNode, LogNode};
+
+    use super::*;
+
+    /// Branch -> log, with the log message read from another store.
+    fn graph() -> Graph {
+        let mut graph = Graph::default();
+
+        let branch = BranchNode::new(&mut graph);
+        let log = LogNode::new(&mut graph);
+        branch.on_false(&mut graph, log.0);
+
+        let message = log.message(&graph).unwrap();
+        let source = graph.add_node(GraphNode::Store(Value::String("say \"hi\"".to_string())));
+        graph.add_edge(source, message.0, GraphEdge::DataFlow);
+
+        graph
+    }
+
+    #[test]
+    fn test_dot() {
+        let dot = to_dot(&graph());
+
+        assert!(dot.starts_with("digraph {\n"));
+        assert!(dot.contains("0 [label=\"lemon.branch\", shape=box]"));
+        assert!(dot.contains("1 [label=\"false\", shape=ellipse]"));
+        assert!(dot.contains("4 [label=\"\\\"say \\\\\\\"hi\\\\\\\"\\\"\", shape=ellipse]"));
+        assert!(dot.contains("1 -> 0 [style=dotted, label=\"condition\"]"));
+        assert!(dot.contains("0 -> 2 [style=bold, label=\"1\"]"));
+        assert!(dot.contains("4 -> 3 [style=dashed]"));
+    }
+
+    #[test]
+    fn test_mermaid() {
+        let mermaid = to_mermaid(&graph());
+
+        assert!(mermaid.starts_with("flowchart TD\n"));
+        assert!(mermaid.contains("n0[\"lemon.branch\"]"));
+        assert!(mermaid.contains("n3([\"#quot;#quot;\"])"));
+        assert!(mermaid.contains("n1 -->|condition| n0"));
+        assert!(mermaid.contains("n0 ==>|1| n2"));
+        assert!(mermaid.contains("n4 -.-> n3"));
+    }
+
+    #[test]
+    fn test_long_value() {
+        let label = node_label(&GraphNode::Store(Value::String("a".repeat(100))));
+        assert_eq!(label.chars().count(), MAX_VALUE_LEN + 3);
+        assert!(label.ends_with("..."));
+    }
+}
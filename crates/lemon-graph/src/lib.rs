@@ -23,39 +23,70 @@
 //!     // Set the log message to the callback output.
 //!     let message = log.message(&graph).unwrap();
 //!     let callback_output = callback.output(&graph).unwrap();
-//!     message.set_input(&mut graph, Some(callback_output));
+//!     message.set_input(&mut graph, Some(callback_output)).unwrap();
 //!
 //!     // Execute the graph.
 //!     Executor::execute(&mut graph, callback.0).await.unwrap();
 //! }
 //! ```
 
-use nodes::{AsyncNode, SyncNode};
+use std::sync::Arc;
+
+use nodes::{AsyncNode, FlowNode, SyncNode};
 use petgraph::graph::DiGraph;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+mod builder;
+mod data;
 mod execution;
+mod export;
 pub mod nodes;
+mod registry;
+mod stream;
+mod validate;
 mod value;
 
+pub use async_trait::async_trait;
+pub use builder::*;
+pub use data::*;
 pub use execution::*;
-pub use value::Value;
+pub use export::*;
+#[cfg(feature = "macros")]
+pub use lemon_graph_macros::graph;
+pub use registry::*;
+pub use stream::{StreamError, StreamSender, ValueStream};
+pub use validate::*;
+pub use value::{Value, ValueKind};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum GraphEdge {
     /// Execution flow between nodes.
-    ExecutionFlow,
+    /// The usize is the index of the execution output in the source node.
+    ExecutionFlow(usize),
     /// Data flow between stores.
     DataFlow,
     /// Data map from node -> store, or store -> node.
     /// The usize is the index of the data in the node.
     DataMap(usize),
+    /// Execution flow followed when the source node fails,
+    /// instead of stopping execution.
+    ErrorFlow,
+    /// Error map from node -> store.
+    /// When the node fails, its error message is written to the store.
+    ErrorMap,
 }
 
 pub enum GraphNode {
     /// Executable async node.
-    AsyncNode(Box<dyn AsyncNode>),
+    /// Shared so it can keep running while the graph is modified.
+    AsyncNode(Arc<dyn AsyncNode>),
     /// Executable sync node.
     SyncNode(Box<dyn SyncNode>),
+    /// Executable sync node that controls the flow of execution.
+    FlowNode(Box<dyn FlowNode>),
     /// Used as an intermediary store for data between nodes.
     Store(Value),
 }
@@ -0,0 +1,127 @@
+use petgraph::graph::NodeIndex;
+
+use crate::{
+    nodes::{Flow, FlowNode, GetStoreError, Node, NodeError, NodePort, NodePorts, Store},
+    Graph, GraphEdge, GraphNode, NodeData, Value, ValueKind,
+};
+
+/// Continues execution through one of two outputs, based on a [Value::Bool] condition.
+#[derive(Debug, Clone, Copy)]
+pub struct BranchNode(pub NodeIndex);
+
+impl From<BranchNode> for NodeIndex {
+    fn from(value: BranchNode) -> Self {
+        value.0
+    }
+}
+
+impl Node for BranchNode {}
+
+impl BranchNode {
+    /// Execution output followed when the condition is true.
+    pub const TRUE: usize = 0;
+    /// Execution output followed when the condition is false.
+    pub const FALSE: usize = 1;
+
+    pub fn new(graph: &mut Graph) -> Self {
+        let index = graph.add_node(GraphNode::FlowNode(Box::new(BranchWeight)));
+
+        let input = graph.add_node(GraphNode::Store(Value::Bool(false)));
+        graph.add_edge(input, index, GraphEdge::DataMap(0));
+
+        Self(index)
+    }
+
+    pub fn condition(&self, graph: &Graph) -> Result<Store, GetStoreError> {
+        self.input_port(graph, "condition")
+    }
+
+    /// Runs the given node when the condition is true.
+    pub fn on_true(&self, graph: &mut Graph, node: NodeIndex) {
+        self.run_before_output(graph, node, Self::TRUE);
+    }
+
+    /// Runs the given node when the condition is false.
+    pub fn on_false(&self, graph: &mut Graph, node: NodeIndex) {
+        self.run_before_output(graph, node, Self::FALSE);
+    }
+}
+
+pub(crate) struct BranchWeight;
+
+impl BranchWeight {
+    pub const NAME: &'static str = "lemon.branch";
+}
+
+impl FlowNode for BranchWeight {
+    fn run(&self, inputs: Vec<Value>) -> Result<(Vec<Value>, Flow), NodeError> {
+        let output = match inputs.first() {
+            Some(Value::Bool(true)) => BranchNode::TRUE,
+            Some(Value::Bool(false)) => BranchNode::FALSE,
+            Some(value) => return Err(NodeError::ConversionError(value.clone())),
+            None => return Err(NodeError::MissingInput(0)),
+        };
+
+        Ok((Vec::new(), Flow::Output(output)))
+    }
+
+    fn data(&self) -> Option<NodeData> {
+        Some(NodeData::new(Self::NAME))
+    }
+
+    fn ports(&self) -> NodePorts<'_> {
+        NodePorts {
+            inputs: vec![NodePort::new("condition", ValueKind::Bool)],
+            outputs: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::{nodes::CallbackNode, Executor};
+
+    use super::*;
+
+    #[test]
+    fn test_branch_weight() {
+        let weight = BranchWeight;
+
+        let (_, flow) = weight.run(vec![Value::Bool(true)]).unwrap();
+        assert_eq!(flow, Flow::Output(BranchNode::TRUE));
+
+        let (_, flow) = weight.run(vec![Value::Bool(false)]).unwrap();
+        assert_eq!(flow, Flow::Output(BranchNode::FALSE));
+
+        let res = weight.run(vec![Value::String("true".to_string())]);
+        assert!(matches!(res, Err(NodeError::ConversionError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_branch() {
+        for condition in [true, false] {
+            let mut graph = Graph::default();
+            let visited = Arc::new(Mutex::new(Vec::new()));
+
+            let branch = BranchNode::new(&mut graph);
+            let input = branch.condition(&graph).unwrap();
+            input.set_value(&mut graph, Value::Bool(condition));
+
+            for (name, output) in [("true", BranchNode::TRUE), ("false", BranchNode::FALSE)] {
+                let visited = visited.clone();
+                let node = CallbackNode::new(&mut graph, move |value| {
+                    visited.lock().unwrap().push(name);
+                    value
+                });
+                branch.run_before_output(&mut graph, node.0, output);
+            }
+
+            Executor::execute(&mut graph, branch.0).await.unwrap();
+
+            let expected = if condition { "true" } else { "false" };
+            assert_eq!(*visited.lock().unwrap(), vec![expected]);
+        }
+    }
+}
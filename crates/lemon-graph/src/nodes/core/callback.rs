@@ -1,8 +1,11 @@
+use std::{convert::Infallible, future::Future, pin::Pin, sync::Arc};
+
+use async_trait::async_trait;
 use petgraph::graph::NodeIndex;
 
 use crate::{
-    nodes::{GetStoreError, Node, NodeError, Store, SyncNode},
-    Graph, GraphEdge, GraphNode, Value,
+    nodes::{AsyncNode, GetStoreError, Node, NodeError, NodePort, NodePorts, Store, SyncNode},
+    Graph, GraphEdge, GraphNode, Value, ValueKind,
 };
 
 /// General purpose node that runs a provided callback.
@@ -18,69 +21,211 @@ impl From<CallbackNode> for NodeIndex {
 impl Node for CallbackNode {}
 
 impl CallbackNode {
-    pub fn new(graph: &mut Graph, cb: impl Fn(Value) -> Value + 'static) -> Self {
+    pub fn new(graph: &mut Graph, cb: impl Fn(Value) -> Value + Send + Sync + 'static) -> Self {
+        Self::try_new(graph, move |input| Ok::<_, Infallible>(cb(input)))
+    }
+
+    /// Creates a node whose callback can fail.
+    /// String errors are returned as a [NodeError::InternalError].
+    pub fn try_new<E: Into<NodeError>>(
+        graph: &mut Graph,
+        cb: impl Fn(Value) -> Result<Value, E> + Send + Sync + 'static,
+    ) -> Self {
+        Self::with_ports(graph, &["input"], &["output"], move |inputs| {
+            let input = inputs.into_iter().next().unwrap_or(Value::Null);
+            cb(input).map(|output| vec![output])
+        })
+    }
+
+    /// Creates a node with the given named inputs and outputs.
+    /// The callback receives a value for each input, and returns a value for each output.
+    /// String errors are returned as a [NodeError::InternalError].
+    pub fn with_ports<E: Into<NodeError>>(
+        graph: &mut Graph,
+        inputs: &[&str],
+        outputs: &[&str],
+        cb: impl Fn(Vec<Value>) -> Result<Vec<Value>, E> + Send + Sync + 'static,
+    ) -> Self {
         let index = graph.add_node(GraphNode::SyncNode(Box::new(CallbackWeight {
-            cb: Box::new(cb),
+            cb: Box::new(move |inputs| cb(inputs).map_err(Into::into)),
+            ports: Ports::new(inputs, outputs),
         })));
 
-        let input = graph.add_node(GraphNode::Store(Value::String(Default::default())));
-        graph.add_edge(input, index, GraphEdge::DataMap(0));
-
-        let output = graph.add_node(GraphNode::Store(Value::String(Default::default())));
-        graph.add_edge(index, output, GraphEdge::DataMap(0));
+        add_stores(graph, index, inputs.len(), outputs.len());
 
         Self(index)
     }
 
     pub fn input(&self, graph: &Graph) -> Result<Store, GetStoreError> {
-        self.input_stores(graph)
-            .next()
-            .ok_or(GetStoreError::NoStore)
+        self.input_port(graph, "input")
     }
 
     pub fn output(&self, graph: &Graph) -> Result<Store, GetStoreError> {
-        self.output_stores(graph)
-            .next()
-            .ok_or(GetStoreError::NoStore)
+        self.output_port(graph, "output")
+    }
+}
+
+/// General purpose node that runs a provided async callback,
+/// such as one that queries a database.
+#[derive(Debug, Clone, Copy)]
+pub struct AsyncCallbackNode(pub NodeIndex);
+
+impl From<AsyncCallbackNode> for NodeIndex {
+    fn from(value: AsyncCallbackNode) -> Self {
+        value.0
+    }
+}
+
+impl Node for AsyncCallbackNode {}
+
+impl AsyncCallbackNode {
+    /// Creates a node with the given named inputs and outputs.
+    /// The callback receives a value for each input, and returns a value for each output.
+    /// String errors are returned as a [NodeError::InternalError].
+    pub fn new<F, E>(
+        graph: &mut Graph,
+        inputs: &[&str],
+        outputs: &[&str],
+        cb: impl Fn(Vec<Value>) -> F + Send + Sync + 'static,
+    ) -> Self
+    where
+        F: Future<Output = Result<Vec<Value>, E>> + Send + 'static,
+        E: Into<NodeError>,
+    {
+        let index = graph.add_node(GraphNode::AsyncNode(Arc::new(AsyncCallbackWeight {
+            cb: Box::new(move |inputs| {
+                let future = cb(inputs);
+                Box::pin(async move { future.await.map_err(Into::into) })
+            }),
+            ports: Ports::new(inputs, outputs),
+        })));
+
+        add_stores(graph, index, inputs.len(), outputs.len());
+
+        Self(index)
+    }
+}
+
+fn add_stores(graph: &mut Graph, index: NodeIndex, inputs: usize, outputs: usize) {
+    for i in 0..inputs {
+        let input = graph.add_node(GraphNode::Store(Value::String(Default::default())));
+        graph.add_edge(input, index, GraphEdge::DataMap(i));
+    }
+
+    for i in 0..outputs {
+        let output = graph.add_node(GraphNode::Store(Value::String(Default::default())));
+        graph.add_edge(index, output, GraphEdge::DataMap(i));
+    }
+}
+
+/// Names of the ports of a callback node.
+struct Ports {
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+}
+
+impl Ports {
+    fn new(inputs: &[&str], outputs: &[&str]) -> Self {
+        Self {
+            inputs: inputs.iter().map(|name| name.to_string()).collect(),
+            outputs: outputs.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    /// Checks that there is a value for each input.
+    fn check(&self, inputs: &[Value]) -> Result<(), NodeError> {
+        if inputs.len() < self.inputs.len() {
+            return Err(NodeError::MissingInput(inputs.len()));
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the callback returned a value for each output.
+    fn check_outputs(&self, outputs: Vec<Value>) -> Result<Vec<Value>, NodeError> {
+        if outputs.len() != self.outputs.len() {
+            return Err(NodeError::OutputCount {
+                expected: self.outputs.len(),
+                got: outputs.len(),
+            });
+        }
+
+        Ok(outputs)
+    }
+
+    /// Returns the ports, which accept and produce any value.
+    fn node_ports<'a>(&'a self) -> NodePorts<'a> {
+        let port = |name: &'a String| NodePort::new(name, ValueKind::Any);
+
+        NodePorts {
+            inputs: self.inputs.iter().map(port).collect(),
+            outputs: self.outputs.iter().map(port).collect(),
+        }
     }
 }
 
+type Callback = Box<dyn Fn(Vec<Value>) -> Result<Vec<Value>, NodeError> + Send + Sync>;
+
 struct CallbackWeight {
-    cb: Box<dyn Fn(Value) -> Value>,
+    cb: Callback,
+    ports: Ports,
 }
 
 impl SyncNode for CallbackWeight {
     fn run(&self, inputs: Vec<Value>) -> Result<Vec<Value>, NodeError> {
-        let input = inputs
-            .first()
-            .ok_or(NodeError::InternalError("No input".to_string()))?;
+        self.ports.check(&inputs)?;
+        self.ports.check_outputs((self.cb)(inputs)?)
+    }
+
+    fn ports(&self) -> NodePorts<'_> {
+        self.ports.node_ports()
+    }
+}
+
+type AsyncCallback = Box<
+    dyn Fn(Vec<Value>) -> Pin<Box<dyn Future<Output = Result<Vec<Value>, NodeError>> + Send>>
+        + Send
+        + Sync,
+>;
+
+struct AsyncCallbackWeight {
+    cb: AsyncCallback,
+    ports: Ports,
+}
 
-        let output = (self.cb)(input.clone());
+#[async_trait]
+impl AsyncNode for AsyncCallbackWeight {
+    async fn run(&self, inputs: Vec<Value>) -> Result<Vec<Value>, NodeError> {
+        self.ports.check(&inputs)?;
+        self.ports.check_outputs((self.cb)(inputs).await?)
+    }
 
-        Ok(vec![output])
+    fn ports(&self) -> NodePorts<'_> {
+        self.ports.node_ports()
     }
 }
 
 #[cfg(test)]
 mod tests {
 
-    use crate::Executor;
+    use crate::{ExecutionStepError, Executor};
 
     use super::*;
 
     #[test]
     fn test_callback_weight() {
         let weight = CallbackWeight {
-            cb: Box::new(|input| {
-                let input = match input {
+            cb: Box::new(|inputs| {
+                let input = match &inputs[0] {
                     Value::String(value) => value,
                     _ => panic!("Invalid input"),
                 };
 
                 let input = input.to_uppercase();
 
-                Value::String(input)
+                Ok(vec![Value::String(input)])
             }),
+            ports: Ports::new(&["input"], &["output"]),
         };
 
         let out = weight
@@ -88,6 +233,9 @@ mod tests {
             .unwrap();
 
         assert_eq!(out, vec!["HELLO, WORLD!".to_string().into()]);
+
+        let res = weight.run(Vec::new());
+        assert!(matches!(res, Err(NodeError::MissingInput(0))));
     }
 
     #[tokio::test]
@@ -121,8 +269,134 @@ mod tests {
 
         let output = callback.output(&graph).unwrap();
         let input = callback_2.input(&graph).unwrap();
-        input.set_input(&mut graph, Some(output));
+        input.set_input(&mut graph, Some(output)).unwrap();
 
         Executor::execute(&mut graph, callback.0).await.unwrap();
     }
+
+    fn add(inputs: Vec<Value>) -> Result<Vec<Value>, String> {
+        match inputs.as_slice() {
+            [Value::USize(a), Value::USize(b)] => Ok(vec![Value::USize(a + b), Value::Bool(a > b)]),
+            _ => Err("Expected two numbers".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_callback_ports() {
+        let mut graph = Graph::default();
+
+        let node = CallbackNode::with_ports(&mut graph, &["a", "b"], &["sum", "greater"], add);
+
+        let a = node.port(&graph, "a").unwrap();
+        a.set_value(&mut graph, Value::USize(3));
+        let b = node.port(&graph, "b").unwrap();
+        b.set_value(&mut graph, Value::USize(2));
+
+        Executor::execute(&mut graph, node.0).await.unwrap();
+
+        let sum = node.port(&graph, "sum").unwrap();
+        assert!(matches!(graph[sum.0], GraphNode::Store(Value::USize(5))));
+        let greater = node.port(&graph, "greater").unwrap();
+        assert!(matches!(
+            graph[greater.0],
+            GraphNode::Store(Value::Bool(true))
+        ));
+
+        // Errors from the callback are returned as node errors.
+        b.set_value(&mut graph, Value::Bool(true));
+        let res = Executor::execute(&mut graph, node.0).await;
+        assert!(matches!(
+            res,
+            Err(ExecutionStepError::NodeError(NodeError::InternalError(e))) if e == "Expected two numbers"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_try_callback() {
+        let mut graph = Graph::default();
+
+        let node = CallbackNode::try_new(&mut graph, |input| match input {
+            Value::String(value) => value
+                .parse::<usize>()
+                .map(Value::USize)
+                .map_err(|e| e.to_string()),
+            value => Ok(value),
+        });
+
+        let input = node.input(&graph).unwrap();
+        input.set_value(&mut graph, Value::String("12".to_string()));
+        Executor::execute(&mut graph, node.0).await.unwrap();
+
+        let output = node.output(&graph).unwrap();
+        assert!(matches!(
+            graph[output.0],
+            GraphNode::Store(Value::USize(12))
+        ));
+
+        input.set_value(&mut graph, Value::String("twelve".to_string()));
+        let res = Executor::execute(&mut graph, node.0).await;
+        assert!(matches!(
+            res,
+            Err(ExecutionStepError::NodeError(NodeError::InternalError(_)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_callback_errors() {
+        let mut graph = Graph::default();
+
+        // Node errors returned by the callback are kept.
+        let node = CallbackNode::try_new(&mut graph, |input| match input {
+            Value::USize(value) => Ok(Value::USize(value * 2)),
+            value => Err(NodeError::ConversionError(value)),
+        });
+
+        let input = node.input(&graph).unwrap();
+        input.set_value(&mut graph, Value::Bool(true));
+        let res = Executor::execute(&mut graph, node.0).await;
+        assert!(matches!(
+            res,
+            Err(ExecutionStepError::NodeError(NodeError::ConversionError(
+                Value::Bool(true)
+            )))
+        ));
+
+        // The callback must return a value for each output.
+        let node = CallbackNode::with_ports(&mut graph, &[], &["a", "b"], |_| {
+            Ok::<_, NodeError>(vec![Value::Null])
+        });
+        let res = Executor::execute(&mut graph, node.0).await;
+        assert!(matches!(
+            res,
+            Err(ExecutionStepError::NodeError(NodeError::OutputCount {
+                expected: 2,
+                got: 1
+            }))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_async_callback() {
+        let mut graph = Graph::default();
+
+        let node = AsyncCallbackNode::new(
+            &mut graph,
+            &["a", "b"],
+            &["sum", "greater"],
+            |inputs| async move {
+                tokio::task::yield_now().await;
+                add(inputs)
+            },
+        );
+
+        let a = node.port(&graph, "a").unwrap();
+        a.set_value(&mut graph, Value::USize(1));
+        let b = node.port(&graph, "b").unwrap();
+        b.set_value(&mut graph, Value::USize(2));
+
+        Executor::execute(&mut graph, node.0).await.unwrap();
+
+        let sum = node.port(&graph, "sum").unwrap();
+        assert!(matches!(graph[sum.0], GraphNode::Store(Value::USize(3))));
+    }
 }
@@ -0,0 +1,262 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use petgraph::graph::NodeIndex;
+
+use crate::{
+    nodes::{Flow, FlowNode, GetStoreError, Node, NodeError, NodePort, NodePorts, Store},
+    Graph, GraphEdge, GraphNode, NodeData, Value, ValueKind,
+};
+
+/// Runs a body of execution once for each element of a [Value::Vec].
+///
+/// Before each run of the body, the current item and its index are written to
+/// the output stores. Once every element has been processed, execution continues
+/// through the completed output.
+#[derive(Debug, Clone, Copy)]
+pub struct ForEachNode(pub NodeIndex);
+
+impl From<ForEachNode> for NodeIndex {
+    fn from(value: ForEachNode) -> Self {
+        value.0
+    }
+}
+
+impl Node for ForEachNode {}
+
+impl ForEachNode {
+    /// Execution output followed for each element.
+    pub const BODY: usize = 0;
+    /// Execution output followed after all elements have been processed.
+    pub const COMPLETED: usize = 1;
+
+    pub fn new(graph: &mut Graph) -> Self {
+        let index = graph.add_node(GraphNode::FlowNode(Box::new(ForEachWeight::default())));
+
+        let input = graph.add_node(GraphNode::Store(Value::Vec(Vec::new())));
+        graph.add_edge(input, index, GraphEdge::DataMap(0));
+
+        let item = graph.add_node(GraphNode::Store(Value::String(Default::default())));
+        graph.add_edge(index, item, GraphEdge::DataMap(0));
+
+        let item_index = graph.add_node(GraphNode::Store(Value::USize(0)));
+        graph.add_edge(index, item_index, GraphEdge::DataMap(1));
+
+        Self(index)
+    }
+
+    pub fn items(&self, graph: &Graph) -> Result<Store, GetStoreError> {
+        self.input_port(graph, "items")
+    }
+
+    /// Store containing the current item.
+    pub fn item(&self, graph: &Graph) -> Result<Store, GetStoreError> {
+        self.output_port(graph, "item")
+    }
+
+    /// Store containing the index of the current item.
+    pub fn index(&self, graph: &Graph) -> Result<Store, GetStoreError> {
+        self.output_port(graph, "index")
+    }
+
+    /// Runs the given node for each element.
+    pub fn body(&self, graph: &mut Graph, node: NodeIndex) {
+        self.run_before_output(graph, node, Self::BODY);
+    }
+
+    /// Runs the given node after all elements have been processed.
+    pub fn completed(&self, graph: &mut Graph, node: NodeIndex) {
+        self.run_before_output(graph, node, Self::COMPLETED);
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct ForEachWeight {
+    next: AtomicUsize,
+}
+
+impl ForEachWeight {
+    pub const NAME: &'static str = "lemon.for_each";
+}
+
+impl FlowNode for ForEachWeight {
+    fn run(&self, inputs: Vec<Value>) -> Result<(Vec<Value>, Flow), NodeError> {
+        let items = match inputs.into_iter().next() {
+            Some(Value::Vec(items)) => items,
+            Some(value) => return Err(NodeError::ConversionError(value)),
+            None => return Err(NodeError::MissingInput(0)),
+        };
+
+        let index = self.next.load(Ordering::SeqCst);
+
+        match items.into_iter().nth(index) {
+            Some(item) => {
+                self.next.store(index + 1, Ordering::SeqCst);
+                Ok((
+                    vec![item, Value::USize(index)],
+                    Flow::Loop(ForEachNode::BODY),
+                ))
+            }
+            None => {
+                self.next.store(0, Ordering::SeqCst);
+                Ok((Vec::new(), Flow::Output(ForEachNode::COMPLETED)))
+            }
+        }
+    }
+
+    fn reset(&self) {
+        self.next.store(0, Ordering::SeqCst);
+    }
+
+    fn data(&self) -> Option<NodeData> {
+        Some(NodeData::new(Self::NAME))
+    }
+
+    fn ports(&self) -> NodePorts<'_> {
+        NodePorts {
+            inputs: vec![NodePort::new("items", ValueKind::Vec)],
+            outputs: vec![
+                NodePort::new("item", ValueKind::Any),
+                NodePort::new("index", ValueKind::USize),
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::{
+        async_trait,
+        nodes::{AsyncNode, CallbackNode},
+        Executor,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_for_each_weight() {
+        let weight = ForEachWeight::default();
+        let items = vec![Value::Vec(vec![Value::Bool(true), Value::Bool(false)])];
+
+        let (outputs, flow) = weight.run(items.clone()).unwrap();
+        assert_eq!(outputs, vec![Value::Bool(true), Value::USize(0)]);
+        assert_eq!(flow, Flow::Loop(ForEachNode::BODY));
+
+        let (outputs, _) = weight.run(items.clone()).unwrap();
+        assert_eq!(outputs, vec![Value::Bool(false), Value::USize(1)]);
+
+        let (outputs, flow) = weight.run(items).unwrap();
+        assert!(outputs.is_empty());
+        assert_eq!(flow, Flow::Output(ForEachNode::COMPLETED));
+    }
+
+    /// Async node that yields before passing its inputs through.
+    struct Yield;
+
+    #[async_trait]
+    impl AsyncNode for Yield {
+        async fn run(&self, inputs: Vec<Value>) -> Result<Vec<Value>, NodeError> {
+            tokio::task::yield_now().await;
+            Ok(inputs)
+        }
+    }
+
+    async fn run_for_each(executor: Executor) {
+        let mut graph = Graph::default();
+        let visited = Arc::new(Mutex::new(Vec::new()));
+
+        let for_each = ForEachNode::new(&mut graph);
+        let items = for_each.items(&graph).unwrap();
+        items.set_value(
+            &mut graph,
+            Value::Vec(vec!["a".to_string().into(), "b".to_string().into()]),
+        );
+
+        // Body: yield -> record the item and index.
+        let body = graph.add_node(GraphNode::AsyncNode(Arc::new(Yield)));
+        for_each.body(&mut graph, body);
+
+        let record = {
+            let visited = visited.clone();
+            CallbackNode::new(&mut graph, move |value| {
+                visited.lock().unwrap().push(value.clone());
+                value
+            })
+        };
+        record.run_after(&mut graph, body);
+
+        let item = for_each.item(&graph).unwrap();
+        let record_input = record.input(&graph).unwrap();
+        record_input.set_input(&mut graph, Some(item)).unwrap();
+
+        let record_index = {
+            let visited = visited.clone();
+            CallbackNode::new(&mut graph, move |value| {
+                visited.lock().unwrap().push(value.clone());
+                value
+            })
+        };
+        record_index.run_after(&mut graph, record.0);
+
+        let index = for_each.index(&graph).unwrap();
+        let index_input = record_index.input(&graph).unwrap();
+        index_input.set_input(&mut graph, Some(index)).unwrap();
+
+        // Completed: record a marker.
+        let done = {
+            let visited = visited.clone();
+            CallbackNode::new(&mut graph, move |value| {
+                visited.lock().unwrap().push(Value::Bool(true));
+                value
+            })
+        };
+        for_each.completed(&mut graph, done.0);
+
+        executor.run(&mut graph, for_each.0).await.unwrap();
+
+        assert_eq!(
+            *visited.lock().unwrap(),
+            vec![
+                "a".to_string().into(),
+                Value::USize(0),
+                "b".to_string().into(),
+                Value::USize(1),
+                Value::Bool(true),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_for_each() {
+        run_for_each(Executor::default()).await;
+    }
+
+    #[tokio::test]
+    async fn test_for_each_concurrent() {
+        run_for_each(Executor {
+            concurrency: 4,
+            ..Default::default()
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_for_each_steps() {
+        let mut graph = Graph::default();
+
+        let for_each = ForEachNode::new(&mut graph);
+        let items = for_each.items(&graph).unwrap();
+        items.set_value(&mut graph, Value::Vec(vec![Value::Bool(true)]));
+
+        let body = CallbackNode::new(&mut graph, |value| value);
+        for_each.body(&mut graph, body.0);
+
+        // Loop steps repeat the node after its body.
+        let step = crate::ExecutionStep(for_each.0);
+        let next = step.execute(&mut graph).await.unwrap().collect::<Vec<_>>();
+        let next = next.into_iter().map(|step| step.0).collect::<Vec<_>>();
+
+        assert_eq!(next, vec![for_each.0, body.0]);
+    }
+}
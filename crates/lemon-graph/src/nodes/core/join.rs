@@ -0,0 +1,268 @@
+use std::{collections::HashSet, sync::Mutex};
+
+use petgraph::{graph::NodeIndex, visit::EdgeRef, Direction};
+
+use crate::{
+    nodes::{Flow, FlowNode, GetStoreError, Node, NodeError, NodePort, NodePorts, Store},
+    Graph, GraphEdge, GraphNode, NodeData, Value, ValueKind,
+};
+
+/// Waits for multiple branches of execution before continuing.
+///
+/// Each node with an execution flow into the join is a branch.
+/// Once every branch has arrived within the current execution, the input store
+/// of each branch is copied to its output store and execution continues.
+/// A branch arriving again before the others only counts once.
+#[derive(Debug, Clone, Copy)]
+pub struct JoinNode(pub NodeIndex);
+
+impl From<JoinNode> for NodeIndex {
+    fn from(value: JoinNode) -> Self {
+        value.0
+    }
+}
+
+impl Node for JoinNode {}
+
+impl JoinNode {
+    /// Creates a join with input and output stores for the given number of branches.
+    pub fn new(graph: &mut Graph, branches: usize) -> Self {
+        let index = graph.add_node(GraphNode::FlowNode(Box::new(JoinWeight::new(branches))));
+
+        for i in 0..branches {
+            let input = graph.add_node(GraphNode::Store(Value::String(Default::default())));
+            graph.add_edge(input, index, GraphEdge::DataMap(i));
+
+            let output = graph.add_node(GraphNode::Store(Value::String(Default::default())));
+            graph.add_edge(index, output, GraphEdge::DataMap(i));
+        }
+
+        Self(index)
+    }
+
+    /// Returns the input store of the given branch.
+    pub fn input(&self, graph: &Graph, branch: usize) -> Result<Store, GetStoreError> {
+        self.input_store(graph, branch)
+    }
+
+    /// Returns the output store of the given branch.
+    pub fn output(&self, graph: &Graph, branch: usize) -> Result<Store, GetStoreError> {
+        self.output_store(graph, branch)
+    }
+}
+
+pub(crate) struct JoinWeight {
+    /// Number of branches with data to pass through.
+    branches: usize,
+    state: Mutex<JoinState>,
+}
+
+impl JoinWeight {
+    pub const NAME: &'static str = "lemon.join";
+
+    pub fn new(branches: usize) -> Self {
+        Self {
+            branches,
+            state: Mutex::default(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct JoinState {
+    /// Branches that have arrived since the join last fired.
+    arrived: HashSet<NodeIndex>,
+    /// Number of times every branch has arrived, not yet run.
+    ready: usize,
+}
+
+impl FlowNode for JoinWeight {
+    fn run(&self, inputs: Vec<Value>) -> Result<(Vec<Value>, Flow), NodeError> {
+        let mut state = self.state.lock().unwrap();
+
+        if state.ready == 0 {
+            return Ok((Vec::new(), Flow::Stop));
+        }
+
+        state.ready -= 1;
+
+        Ok((inputs, Flow::Continue))
+    }
+
+    fn reset(&self) {
+        *self.state.lock().unwrap() = JoinState::default();
+    }
+
+    fn data(&self) -> Option<NodeData> {
+        Some(NodeData {
+            name: Self::NAME.to_string(),
+            config: Some(Value::USize(self.branches)),
+        })
+    }
+
+    fn ports(&self) -> NodePorts<'_> {
+        let ports = vec![NodePort::new("", ValueKind::Any); self.branches];
+
+        NodePorts {
+            inputs: ports.clone(),
+            outputs: ports,
+        }
+    }
+
+    fn arrive(&self, graph: &Graph, node: NodeIndex, from: NodeIndex) {
+        let mut state = self.state.lock().unwrap();
+        state.arrived.insert(from);
+
+        let all_arrived = graph
+            .edges_directed(node, Direction::Incoming)
+            .filter(|edge| {
+                matches!(
+                    edge.weight(),
+                    GraphEdge::ExecutionFlow(_) | GraphEdge::ErrorFlow
+                )
+            })
+            .all(|edge| state.arrived.contains(&edge.source()));
+
+        if all_arrived {
+            state.arrived.clear();
+            state.ready += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::{
+        nodes::{CallbackNode, ForEachNode},
+        Executor,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_join_weight() {
+        let mut graph = Graph::default();
+
+        let a = CallbackNode::new(&mut graph, |value| value);
+        let b = CallbackNode::new(&mut graph, |value| value);
+        let join = JoinNode::new(&mut graph, 0);
+        join.run_after(&mut graph, a.0);
+        join.run_after(&mut graph, b.0);
+
+        let GraphNode::FlowNode(weight) = &graph[join.0] else {
+            panic!();
+        };
+
+        let inputs = vec![Value::Bool(true), Value::Bool(false)];
+
+        weight.arrive(&graph, join.0, a.0);
+        let (outputs, flow) = weight.run(inputs.clone()).unwrap();
+        assert!(outputs.is_empty());
+        assert_eq!(flow, Flow::Stop);
+
+        // A branch arriving twice only counts once.
+        weight.arrive(&graph, join.0, a.0);
+        assert_eq!(weight.run(inputs.clone()).unwrap().1, Flow::Stop);
+
+        weight.arrive(&graph, join.0, b.0);
+        let (outputs, flow) = weight.run(inputs.clone()).unwrap();
+        assert_eq!(outputs, inputs);
+        assert_eq!(flow, Flow::Continue);
+
+        weight.arrive(&graph, join.0, a.0);
+        weight.reset();
+        weight.arrive(&graph, join.0, b.0);
+        assert_eq!(weight.run(inputs).unwrap().1, Flow::Stop);
+    }
+
+    #[tokio::test]
+    async fn test_join() {
+        let mut graph = Graph::default();
+
+        let start = CallbackNode::new(&mut graph, |value| value);
+        let join = JoinNode::new(&mut graph, 2);
+
+        for (branch, text) in ["a", "b"].into_iter().enumerate() {
+            let node = CallbackNode::new(&mut graph, move |_| Value::String(text.to_string()));
+            node.run_after(&mut graph, start.0);
+            join.run_after(&mut graph, node.0);
+
+            let output = node.output(&graph).unwrap();
+            join.input(&graph, branch)
+                .unwrap()
+                .set_input(&mut graph, Some(output))
+                .unwrap();
+        }
+
+        let results = Arc::new(Mutex::new(Vec::new()));
+
+        for branch in 0..2 {
+            let results = results.clone();
+            let node = CallbackNode::new(&mut graph, move |value| {
+                results.lock().unwrap().push(value.clone());
+                value
+            });
+            node.run_after(&mut graph, join.0);
+
+            let output = join.output(&graph, branch).unwrap();
+            node.input(&graph)
+                .unwrap()
+                .set_input(&mut graph, Some(output))
+                .unwrap();
+        }
+
+        Executor::execute(&mut graph, start.0).await.unwrap();
+
+        let mut results = results.lock().unwrap().clone();
+        results.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        assert_eq!(
+            results,
+            vec![Value::String("a".into()), Value::String("b".into())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_branch_arrives_twice() {
+        let mut graph = Graph::default();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let logged = |graph: &mut Graph, name: &'static str| {
+            let log = log.clone();
+            CallbackNode::new(graph, move |value| {
+                log.lock().unwrap().push(name);
+                value
+            })
+        };
+
+        // The first branch loops twice, arriving at the join each time.
+        let for_each = ForEachNode::new(&mut graph);
+        let items = for_each.items(&graph).unwrap();
+        items.set_value(
+            &mut graph,
+            Value::Vec(vec![Value::USize(1), Value::USize(2)]),
+        );
+
+        let looped = logged(&mut graph, "looped");
+        for_each.body(&mut graph, looped.0);
+
+        let other = logged(&mut graph, "other");
+        for_each.completed(&mut graph, other.0);
+
+        let join = JoinNode::new(&mut graph, 0);
+        join.run_after(&mut graph, looped.0);
+        join.run_after(&mut graph, other.0);
+
+        let after = logged(&mut graph, "after");
+        after.run_after(&mut graph, join.0);
+
+        Executor::execute(&mut graph, for_each.0).await.unwrap();
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec!["looped", "looped", "other", "after"]
+        );
+    }
+}
@@ -2,8 +2,8 @@ use petgraph::graph::NodeIndex;
 use tracing::info;
 
 use crate::{
-    nodes::{GetStoreError, Node, NodeError, Store, SyncNode},
-    Graph, GraphEdge, GraphNode, Value,
+    nodes::{GetStoreError, Node, NodeError, NodePort, NodePorts, Store, SyncNode},
+    Graph, GraphEdge, GraphNode, NodeData, Value, ValueKind,
 };
 
 /// Logs a provided message.
@@ -29,13 +29,15 @@ impl LogNode {
     }
 
     pub fn message(&self, graph: &Graph) -> Result<Store, GetStoreError> {
-        self.input_stores(graph)
-            .next()
-            .ok_or(GetStoreError::NoStore)
+        self.input_port(graph, "message")
     }
 }
 
-struct LogWeight;
+pub(crate) struct LogWeight;
+
+impl LogWeight {
+    pub const NAME: &'static str = "lemon.log";
+}
 
 impl SyncNode for LogWeight {
     fn run(&self, inputs: Vec<Value>) -> Result<Vec<Value>, NodeError> {
@@ -47,6 +49,17 @@ impl SyncNode for LogWeight {
 
         Ok(vec![])
     }
+
+    fn data(&self) -> Option<NodeData> {
+        Some(NodeData::new(Self::NAME))
+    }
+
+    fn ports(&self) -> NodePorts<'_> {
+        NodePorts {
+            inputs: vec![NodePort::new("message", ValueKind::Any)],
+            outputs: Vec::new(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -1,7 +1,79 @@
+mod branch;
 mod callback;
+mod for_each;
+mod join;
 mod log;
 mod prompt;
+mod subgraph;
 
+pub use branch::*;
 pub use callback::*;
+pub use for_each::*;
+pub use join::*;
 pub use log::*;
 pub use prompt::*;
+pub use subgraph::*;
+
+use crate::{GraphNode, LoadError, NodeRegistry, NodeType, Port, Value, ValueKind};
+
+/// Registers the core nodes that can be created by name.
+pub fn register_core(registry: &mut NodeRegistry) {
+    registry.register(NodeType::new(
+        BranchWeight::NAME,
+        vec![Port::new("condition", ValueKind::Bool, Value::Bool(false))],
+        Vec::new(),
+        |_| Ok(GraphNode::FlowNode(Box::new(BranchWeight))),
+    ));
+
+    registry.register(NodeType::new(
+        ForEachWeight::NAME,
+        vec![Port::new("items", ValueKind::Vec, Value::Vec(Vec::new()))],
+        vec![
+            Port::new("item", ValueKind::Any, Value::String(Default::default())),
+            Port::new("index", ValueKind::USize, Value::USize(0)),
+        ],
+        |_| Ok(GraphNode::FlowNode(Box::new(ForEachWeight::default()))),
+    ));
+
+    // Ports depend on the number of branches, see NodeRegistry::create.
+    registry.register(NodeType::new(
+        JoinWeight::NAME,
+        Vec::new(),
+        Vec::new(),
+        |config| {
+            let branches = match config {
+                Some(Value::USize(branches)) => *branches,
+                Some(_) => return Err(LoadError::InvalidConfig(JoinWeight::NAME.to_string())),
+                None => 2,
+            };
+
+            Ok(GraphNode::FlowNode(Box::new(JoinWeight::new(branches))))
+        },
+    ));
+
+    registry.register(NodeType::new(
+        LogWeight::NAME,
+        vec![Port::new(
+            "message",
+            ValueKind::Any,
+            Value::String(Default::default()),
+        )],
+        Vec::new(),
+        |_| Ok(GraphNode::SyncNode(Box::new(LogWeight))),
+    ));
+
+    registry.register(NodeType::new(
+        PromptWeight::NAME,
+        vec![Port::new(
+            "input",
+            ValueKind::String,
+            Value::String(Default::default()),
+        )],
+        vec![Port::new(
+            "output",
+            ValueKind::String,
+            Value::String(Default::default()),
+        )],
+        |_| Ok(GraphNode::SyncNode(Box::new(PromptWeight))),
+    ));
+}
@@ -1,8 +1,8 @@
 use petgraph::graph::NodeIndex;
 
 use crate::{
-    nodes::{GetStoreError, Node, NodeError, Store, SyncNode},
-    Graph, GraphEdge, GraphNode, Value,
+    nodes::{GetStoreError, Node, NodeError, NodePort, NodePorts, Store, SyncNode},
+    Graph, GraphEdge, GraphNode, NodeData, Value, ValueKind,
 };
 
 #[derive(Debug, Clone, Copy)]
@@ -30,19 +30,19 @@ impl PromptNode {
     }
 
     pub fn input(&self, graph: &Graph) -> Result<Store, GetStoreError> {
-        self.input_stores(graph)
-            .next()
-            .ok_or(GetStoreError::NoStore)
+        self.input_port(graph, "input")
     }
 
     pub fn output(&self, graph: &Graph) -> Result<Store, GetStoreError> {
-        self.output_stores(graph)
-            .next()
-            .ok_or(GetStoreError::NoStore)
+        self.output_port(graph, "output")
     }
 }
 
-struct PromptWeight;
+pub(crate) struct PromptWeight;
+
+impl PromptWeight {
+    pub const NAME: &'static str = "lemon.prompt";
+}
 
 impl SyncNode for PromptWeight {
     fn run(&self, inputs: Vec<Value>) -> Result<Vec<Value>, NodeError> {
@@ -65,4 +65,15 @@ impl SyncNode for PromptWeight {
 
         Ok(vec![output_value.trim().to_string().into()])
     }
+
+    fn data(&self) -> Option<NodeData> {
+        Some(NodeData::new(Self::NAME))
+    }
+
+    fn ports(&self) -> NodePorts<'_> {
+        NodePorts {
+            inputs: vec![NodePort::new("input", ValueKind::String)],
+            outputs: vec![NodePort::new("output", ValueKind::String)],
+        }
+    }
 }
@@ -0,0 +1,482 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use async_trait::async_trait;
+
+use petgraph::graph::NodeIndex;
+use tokio::sync::Mutex;
+
+use crate::{
+    nodes::{AsyncNode, GetStoreError, Node, NodeError, NodePort, NodePorts, Store},
+    ExecutionContext, ExecutionEvent, ExecutionStepError, Executor, Graph, GraphEdge, GraphNode,
+    NodeOptions, Value, ValueKind,
+};
+
+/// Graph to be packaged as a single [SubgraphNode].
+pub struct Subgraph {
+    pub graph: Graph,
+    /// Node that execution starts from.
+    pub entry: NodeIndex,
+    /// Node that must be reached for the subgraph to succeed.
+    pub exit: NodeIndex,
+    /// Inner stores set from the inputs of the node, by data index.
+    pub inputs: Vec<Store>,
+    /// Inner stores read into the outputs of the node, by data index.
+    pub outputs: Vec<Store>,
+    /// Maximum number of inner async nodes to run at the same time.
+    pub concurrency: usize,
+    /// Options for inner nodes, such as retry policies or whether they are pure.
+    pub nodes: HashMap<NodeIndex, NodeOptions>,
+}
+
+impl Subgraph {
+    pub fn new(graph: Graph, entry: NodeIndex, exit: NodeIndex) -> Self {
+        Self {
+            graph,
+            entry,
+            exit,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            concurrency: 1,
+            nodes: HashMap::new(),
+        }
+    }
+
+    /// Returns the options for the given inner node, to be modified.
+    pub fn node(&mut self, node: impl Into<NodeIndex>) -> &mut NodeOptions {
+        self.nodes.entry(node.into()).or_default()
+    }
+}
+
+/// Runs another graph as a single node.
+///
+/// Inputs of the node are written to the inner input stores, then the inner
+/// graph is executed from its entry node, sharing the [ExecutionContext] of the
+/// outer execution. Once execution completes, the inner output stores are read
+/// into the outputs of the node.
+///
+/// The inner execution uses the observers, cache, replay and validation of the
+/// outer executor, and its steps count towards the outer limits.
+/// Inner nodes are configured with [Subgraph::nodes].
+/// Events of inner nodes have the index of this node at the end of their
+/// [path](crate::ExecutionEvent::path).
+#[derive(Debug, Clone, Copy)]
+pub struct SubgraphNode(pub NodeIndex);
+
+impl From<SubgraphNode> for NodeIndex {
+    fn from(value: SubgraphNode) -> Self {
+        value.0
+    }
+}
+
+impl Node for SubgraphNode {}
+
+impl SubgraphNode {
+    pub fn new(graph: &mut Graph, subgraph: Subgraph) -> Self {
+        let ports = |stores: &[Store]| {
+            stores
+                .iter()
+                .map(|store| {
+                    let value = match &subgraph.graph[store.0] {
+                        GraphNode::Store(value) => value.clone(),
+                        _ => Value::Null,
+                    };
+                    (store.kind(&subgraph.graph), value)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let inputs = ports(&subgraph.inputs);
+        let outputs = ports(&subgraph.outputs);
+
+        let index = graph.add_node(GraphNode::AsyncNode(Arc::new(SubgraphWeight {
+            input_kinds: inputs.iter().map(|(kind, _)| *kind).collect(),
+            output_kinds: outputs.iter().map(|(kind, _)| *kind).collect(),
+            subgraph: Mutex::new(subgraph),
+        })));
+
+        for (i, (_, value)) in inputs.into_iter().enumerate() {
+            let input = graph.add_node(GraphNode::Store(value));
+            graph.add_edge(input, index, GraphEdge::DataMap(i));
+        }
+
+        for (i, (_, value)) in outputs.into_iter().enumerate() {
+            let output = graph.add_node(GraphNode::Store(value));
+            graph.add_edge(index, output, GraphEdge::DataMap(i));
+        }
+
+        Self(index)
+    }
+
+    /// Returns the store for the input at the given index.
+    pub fn input(&self, graph: &Graph, index: usize) -> Result<Store, GetStoreError> {
+        self.input_store(graph, index)
+    }
+
+    /// Returns the store for the output at the given index.
+    pub fn output(&self, graph: &Graph, index: usize) -> Result<Store, GetStoreError> {
+        self.output_store(graph, index)
+    }
+}
+
+struct SubgraphWeight {
+    subgraph: Mutex<Subgraph>,
+    input_kinds: Vec<ValueKind>,
+    output_kinds: Vec<ValueKind>,
+}
+
+#[async_trait]
+impl AsyncNode for SubgraphWeight {
+    async fn run(&self, inputs: Vec<Value>) -> Result<Vec<Value>, NodeError> {
+        self.run_with_context(&ExecutionContext::default(), inputs)
+            .await
+    }
+
+    async fn run_with_context(
+        &self,
+        ctx: &ExecutionContext,
+        inputs: Vec<Value>,
+    ) -> Result<Vec<Value>, NodeError> {
+        let mut subgraph = self.subgraph.lock().await;
+        let Subgraph {
+            graph,
+            entry,
+            exit,
+            inputs: input_stores,
+            outputs: output_stores,
+            concurrency,
+            nodes,
+        } = &mut *subgraph;
+
+        for (store, value) in input_stores.iter().zip(inputs) {
+            store.set_value(graph, value);
+        }
+
+        let reached = Arc::new(AtomicBool::new(false));
+
+        let executor = {
+            let (exit, reached) = (*exit, reached.clone());
+            let inherited = &ctx.inherited;
+            let path = inherited.path.clone();
+
+            // Nested subgraphs inherit this observer, so check the path too.
+            let mut observers = inherited.observers.clone();
+            observers.push(Arc::new(move |event: &ExecutionEvent| {
+                if let ExecutionEvent::StepFinished { node, .. } = event {
+                    if *node == exit && event.path() == path {
+                        reached.store(true, Ordering::SeqCst);
+                    }
+                }
+            }));
+
+            Executor {
+                concurrency: *concurrency,
+                validate: inherited.validate,
+                nodes: nodes.clone(),
+                observers,
+                replay: inherited.replay.clone(),
+                cache: inherited.cache.clone(),
+                ..Default::default()
+            }
+        };
+
+        let res = executor.run_with_context(graph, *entry, ctx.clone()).await;
+
+        res.map_err(|e| match e {
+            ExecutionStepError::NodeError(e) => e,
+            e => NodeError::InternalError(e.to_string()),
+        })?;
+
+        if !reached.load(Ordering::SeqCst) {
+            return Err(NodeError::InternalError(format!(
+                "Subgraph did not reach exit node {:?}",
+                exit
+            )));
+        }
+
+        output_stores
+            .iter()
+            .map(|store| match &graph[store.0] {
+                GraphNode::Store(value) => Ok(value.clone()),
+                _ => Err(NodeError::InternalError(format!(
+                    "Subgraph output {:?} is not a store",
+                    store.0
+                ))),
+            })
+            .collect()
+    }
+
+    fn ports(&self) -> NodePorts<'_> {
+        let ports =
+            |kinds: &[ValueKind]| kinds.iter().map(|kind| NodePort::new("", *kind)).collect();
+
+        NodePorts {
+            inputs: ports(&self.input_kinds),
+            outputs: ports(&self.output_kinds),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::atomic::AtomicUsize, time::Duration};
+
+    use crate::{
+        nodes::{CallbackNode, SyncNode},
+        Limits, MemoryCache, NodeData, Recorder, Replay,
+    };
+
+    use super::*;
+
+    fn map_string(graph: &mut Graph, f: fn(&str) -> String) -> CallbackNode {
+        CallbackNode::new(graph, move |value| match value {
+            Value::String(value) => Value::String(f(&value)),
+            value => value,
+        })
+    }
+
+    /// Inner graph: trim -> uppercase.
+    fn uppercase() -> Subgraph {
+        let mut graph = Graph::default();
+
+        let trim = map_string(&mut graph, |value| value.trim().to_string());
+        let upper = map_string(&mut graph, str::to_uppercase);
+        upper.run_after(&mut graph, trim.0);
+
+        let output = trim.output(&graph).unwrap();
+        let input = upper.input(&graph).unwrap();
+        input.set_input(&mut graph, Some(output)).unwrap();
+
+        Subgraph {
+            inputs: vec![trim.input(&graph).unwrap()],
+            outputs: vec![upper.output(&graph).unwrap()],
+            ..Subgraph::new(graph, trim.0, upper.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subgraph() {
+        let mut graph = Graph::default();
+
+        let node = SubgraphNode::new(&mut graph, uppercase());
+
+        let input = node.input(&graph, 0).unwrap();
+        input.set_value(&mut graph, Value::String(" hello ".to_string()));
+
+        let result = CallbackNode::new(&mut graph, |value| value);
+        result.run_after(&mut graph, node.0);
+        let output = node.output(&graph, 0).unwrap();
+        let result_input = result.input(&graph).unwrap();
+        result_input.set_input(&mut graph, Some(output)).unwrap();
+
+        Executor::execute(&mut graph, node.0).await.unwrap();
+
+        let output = result.output(&graph).unwrap();
+        assert!(
+            matches!(&graph[output.0], GraphNode::Store(Value::String(value)) if value == "HELLO")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_subgraph_exit_unreached() {
+        let mut subgraph = uppercase();
+        subgraph.exit = subgraph.graph.add_node(GraphNode::Store(Value::Null));
+
+        let mut graph = Graph::default();
+        let node = SubgraphNode::new(&mut graph, subgraph);
+
+        let res = Executor::execute(&mut graph, node.0).await;
+        assert!(matches!(
+            res,
+            Err(ExecutionStepError::NodeError(NodeError::InternalError(_)))
+        ));
+    }
+
+    /// Counts how many times it has run.
+    struct Counter(Arc<AtomicUsize>);
+
+    impl SyncNode for Counter {
+        fn run(&self, _inputs: Vec<Value>) -> Result<Vec<Value>, NodeError> {
+            let count = self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(vec![Value::USize(count)])
+        }
+
+        fn data(&self) -> Option<NodeData> {
+            Some(NodeData::new("test.counter"))
+        }
+    }
+
+    /// Outer graph holding a subgraph with a single counter.
+    fn counter() -> (Graph, SubgraphNode, Arc<AtomicUsize>) {
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let mut inner = Graph::default();
+        let node = inner.add_node(GraphNode::SyncNode(Box::new(Counter(runs.clone()))));
+        let output = inner.add_node(GraphNode::Store(Value::Null));
+        inner.add_edge(node, output, GraphEdge::DataMap(0));
+
+        let mut subgraph = Subgraph {
+            outputs: vec![Store(output)],
+            ..Subgraph::new(inner, node, node)
+        };
+        subgraph.node(node).pure = true;
+
+        let mut graph = Graph::default();
+        let node = SubgraphNode::new(&mut graph, subgraph);
+
+        (graph, node, runs)
+    }
+
+    #[tokio::test]
+    async fn test_subgraph_cache() {
+        let (mut graph, node, runs) = counter();
+
+        let executor = Executor {
+            cache: Some(Arc::new(MemoryCache::default())),
+            ..Default::default()
+        };
+        executor.run(&mut graph, node.0).await.unwrap();
+        executor.run(&mut graph, node.0).await.unwrap();
+
+        // The inner node is pure, so the second run used the cache.
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        let output = node.output(&graph, 0).unwrap();
+        assert!(matches!(graph[output.0], GraphNode::Store(Value::USize(0))));
+    }
+
+    #[tokio::test]
+    async fn test_subgraph_replay() {
+        let (mut graph, node, runs) = counter();
+
+        let recorder = Arc::new(Recorder::default());
+        let executor = Executor {
+            observers: vec![recorder.clone()],
+            ..Default::default()
+        };
+        executor.run(&mut graph, node.0).await.unwrap();
+
+        // Replay the inner node, but not the subgraph node itself.
+        let path = [node.0];
+        let executor = Executor {
+            replay: Some(Replay::new(&recorder.trace(), |p, _| p == path)),
+            ..Default::default()
+        };
+        executor.run(&mut graph, node.0).await.unwrap();
+
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        let output = node.output(&graph, 0).unwrap();
+        assert!(matches!(graph[output.0], GraphNode::Store(Value::USize(0))));
+    }
+
+    /// Inner graph that writes variables, then waits for a long time.
+    fn slow() -> Subgraph {
+        struct Sleep;
+
+        #[async_trait]
+        impl AsyncNode for Sleep {
+            async fn run(&self, _inputs: Vec<Value>) -> Result<Vec<Value>, NodeError> {
+                Ok(Vec::new())
+            }
+
+            async fn run_with_context(
+                &self,
+                ctx: &ExecutionContext,
+                _inputs: Vec<Value>,
+            ) -> Result<Vec<Value>, NodeError> {
+                let greeting = ctx.variables.get("greeting").unwrap_or(Value::Null);
+                ctx.variables.set("inner", greeting);
+                ctx.variables
+                    .set("run_id", Value::String(ctx.run_id.to_string()));
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                Ok(Vec::new())
+            }
+        }
+
+        let mut graph = Graph::default();
+        let node = graph.add_node(GraphNode::AsyncNode(Arc::new(Sleep)));
+        Subgraph::new(graph, node, node)
+    }
+
+    #[tokio::test]
+    async fn test_subgraph_context() {
+        let mut graph = Graph::default();
+        let node = SubgraphNode::new(&mut graph, slow());
+
+        let executor = Executor::default();
+        let ctx = executor.context();
+        ctx.variables
+            .set("greeting", Value::String("hello".to_string()));
+
+        let cancel = ctx.cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            cancel.cancel();
+        });
+
+        // Cancelling the outer execution stops the inner one.
+        let res = tokio::time::timeout(
+            Duration::from_secs(5),
+            executor.run_with_context(&mut graph, node.0, ctx.clone()),
+        )
+        .await
+        .unwrap();
+        assert!(matches!(res, Err(ExecutionStepError::Cancelled)));
+
+        // The inner graph shares the run ID and variables of the outer execution.
+        assert_eq!(
+            ctx.variables.get("inner"),
+            Some(Value::String("hello".to_string()))
+        );
+        assert_eq!(
+            ctx.variables.get("run_id"),
+            Some(Value::String(ctx.run_id.to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_subgraph_limits() {
+        // Inner graph: a -> b -> a, forever.
+        let mut inner = Graph::default();
+        let a = CallbackNode::new(&mut inner, |value| value);
+        let b = CallbackNode::new(&mut inner, |value| value);
+        b.run_after(&mut inner, a.0);
+        a.run_after(&mut inner, b.0);
+
+        let mut graph = Graph::default();
+        let node = SubgraphNode::new(&mut graph, Subgraph::new(inner, a.0, b.0));
+
+        let steps = Arc::new(AtomicUsize::new(0));
+        let counted = steps.clone();
+
+        let executor = Executor {
+            limits: Limits {
+                max_steps: Some(10),
+                ..Default::default()
+            },
+            observers: vec![Arc::new(move |event: &ExecutionEvent| {
+                if let ExecutionEvent::StepStarted { .. } = event {
+                    counted.fetch_add(1, Ordering::SeqCst);
+                }
+            })],
+            ..Default::default()
+        };
+
+        let res = tokio::time::timeout(Duration::from_secs(5), executor.run(&mut graph, node.0))
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            res,
+            Err(ExecutionStepError::NodeError(NodeError::InternalError(message)))
+                if message.contains("Exceeded limit of 10 steps")
+        ));
+
+        // The outer step, then inner steps until the limit, were observed.
+        assert_eq!(steps.load(Ordering::SeqCst), 11);
+    }
+}
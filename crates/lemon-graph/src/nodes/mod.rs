@@ -1,8 +1,10 @@
+use std::convert::Infallible;
+
+use async_trait::async_trait;
 use petgraph::{graph::NodeIndex, visit::EdgeRef, Direction};
-use std::future::Future;
 use thiserror::Error;
 
-use crate::{Graph, GraphEdge, Value};
+use crate::{ExecutionContext, Graph, GraphEdge, GraphNode, NodeData, Value, ValueKind};
 
 mod core;
 mod store;
@@ -18,17 +20,222 @@ pub enum NodeError {
     ConversionError(Value),
     #[error("Internal error: {0}")]
     InternalError(String),
+    #[error("Expected {expected} outputs, got {got}")]
+    OutputCount { expected: usize, got: usize },
+}
+
+impl From<String> for NodeError {
+    fn from(value: String) -> Self {
+        Self::InternalError(value)
+    }
+}
+
+impl From<&str> for NodeError {
+    fn from(value: &str) -> Self {
+        Self::InternalError(value.to_string())
+    }
+}
+
+impl From<Infallible> for NodeError {
+    fn from(value: Infallible) -> Self {
+        match value {}
+    }
 }
 
-pub trait AsyncNode {
-    fn run(
+/// Name and kind of an input or output of a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodePort<'a> {
+    /// Name of the port, empty if it can only be found by index.
+    pub name: &'a str,
+    /// Kind of value the port expects or produces.
+    pub kind: ValueKind,
+}
+
+impl<'a> NodePort<'a> {
+    pub fn new(name: &'a str, kind: ValueKind) -> Self {
+        Self { name, kind }
+    }
+}
+
+/// Inputs and outputs of a node, by data index.
+/// Missing ports accept or produce any value, and can only be found by index.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NodePorts<'a> {
+    pub inputs: Vec<NodePort<'a>>,
+    pub outputs: Vec<NodePort<'a>>,
+}
+
+/// Executable node that runs asynchronously.
+///
+/// Implement with [macro@async_trait]:
+///
+/// ```
+/// use lemon_graph::{async_trait, nodes::{AsyncNode, NodeError}, Value};
+///
+/// struct Echo;
+///
+/// #[async_trait]
+/// impl AsyncNode for Echo {
+///     async fn run(&self, inputs: Vec<Value>) -> Result<Vec<Value>, NodeError> {
+///         Ok(inputs)
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait AsyncNode: Send + Sync {
+    async fn run(&self, inputs: Vec<Value>) -> Result<Vec<Value>, NodeError>;
+
+    /// Runs the node with access to the [ExecutionContext].
+    /// Defaults to [run](AsyncNode::run), ignoring the context.
+    async fn run_with_context(
         &self,
+        _ctx: &ExecutionContext,
         inputs: Vec<Value>,
-    ) -> Box<dyn Future<Output = Result<Vec<Value>, NodeError>> + Unpin>;
+    ) -> Result<Vec<Value>, NodeError> {
+        self.run(inputs).await
+    }
+
+    /// Returns the data needed to save and load this node.
+    /// Nodes that cannot be saved return `None`.
+    fn data(&self) -> Option<NodeData> {
+        None
+    }
+
+    /// Returns the inputs and outputs of the node.
+    fn ports(&self) -> NodePorts<'_> {
+        NodePorts::default()
+    }
 }
 
-pub trait SyncNode {
+pub trait SyncNode: Send + Sync {
     fn run(&self, inputs: Vec<Value>) -> Result<Vec<Value>, NodeError>;
+
+    /// Runs the node with access to the [ExecutionContext].
+    /// Defaults to [run](SyncNode::run), ignoring the context.
+    fn run_with_context(
+        &self,
+        _ctx: &ExecutionContext,
+        inputs: Vec<Value>,
+    ) -> Result<Vec<Value>, NodeError> {
+        self.run(inputs)
+    }
+
+    /// Returns the data needed to save and load this node.
+    /// Nodes that cannot be saved return `None`.
+    fn data(&self) -> Option<NodeData> {
+        None
+    }
+
+    /// Returns the inputs and outputs of the node.
+    fn ports(&self) -> NodePorts<'_> {
+        NodePorts::default()
+    }
+}
+
+/// Controls which execution flows are followed after a [FlowNode] runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    /// Continue through all outgoing execution flows.
+    Continue,
+    /// Continue through the execution flows of the given output.
+    Output(usize),
+    /// Run the execution flows of the given output to completion,
+    /// then run this node again.
+    Loop(usize),
+    /// Stop execution along this path.
+    Stop,
+}
+
+/// Node that controls the flow of execution.
+pub trait FlowNode: Send + Sync {
+    fn run(&self, inputs: Vec<Value>) -> Result<(Vec<Value>, Flow), NodeError>;
+
+    /// Runs the node with access to the [ExecutionContext].
+    /// Defaults to [run](FlowNode::run), ignoring the context.
+    fn run_with_context(
+        &self,
+        _ctx: &ExecutionContext,
+        inputs: Vec<Value>,
+    ) -> Result<(Vec<Value>, Flow), NodeError> {
+        self.run(inputs)
+    }
+
+    /// Resets any state kept between runs.
+    /// Called by the [Executor](crate::Executor) before each execution.
+    fn reset(&self) {}
+
+    /// Called when an execution flow from `from` reaches this node, at `node`,
+    /// before the node is run for it.
+    fn arrive(&self, _graph: &Graph, _node: NodeIndex, _from: NodeIndex) {}
+
+    /// Returns the data needed to save and load this node.
+    /// Nodes that cannot be saved return `None`.
+    fn data(&self) -> Option<NodeData> {
+        None
+    }
+
+    /// Returns the inputs and outputs of the node.
+    fn ports(&self) -> NodePorts<'_> {
+        NodePorts::default()
+    }
+}
+
+impl GraphNode {
+    /// Returns the data used to save the node, if it is an executable node that provides it.
+    pub fn data(&self) -> Option<NodeData> {
+        match self {
+            GraphNode::AsyncNode(node) => node.data(),
+            GraphNode::SyncNode(node) => node.data(),
+            GraphNode::FlowNode(node) => node.data(),
+            GraphNode::Store(_) => None,
+        }
+    }
+
+    /// Returns the inputs and outputs of the node, if it is an executable node.
+    pub fn ports(&self) -> NodePorts<'_> {
+        match self {
+            GraphNode::AsyncNode(node) => node.ports(),
+            GraphNode::SyncNode(node) => node.ports(),
+            GraphNode::FlowNode(node) => node.ports(),
+            GraphNode::Store(_) => NodePorts::default(),
+        }
+    }
+
+    /// Returns the kind of value expected by the input at the given data index.
+    pub fn input_kind(&self, index: usize) -> ValueKind {
+        let ports = self.ports();
+        ports
+            .inputs
+            .get(index)
+            .map(|port| port.kind)
+            .unwrap_or_default()
+    }
+
+    /// Returns the kind of value produced by the output at the given data index.
+    pub fn output_kind(&self, index: usize) -> ValueKind {
+        let ports = self.ports();
+        ports
+            .outputs
+            .get(index)
+            .map(|port| port.kind)
+            .unwrap_or_default()
+    }
+
+    /// Returns the data index of the input with the given name.
+    pub fn input_index(&self, name: &str) -> Option<usize> {
+        self.ports()
+            .inputs
+            .iter()
+            .position(|port| !name.is_empty() && port.name == name)
+    }
+
+    /// Returns the data index of the output with the given name.
+    pub fn output_index(&self, name: &str) -> Option<usize> {
+        self.ports()
+            .outputs
+            .iter()
+            .position(|port| !name.is_empty() && port.name == name)
+    }
 }
 
 pub trait Node: Copy + Into<NodeIndex> {
@@ -45,26 +252,96 @@ pub trait Node: Copy + Into<NodeIndex> {
             .map(|edge| Store(edge.target()))
     }
 
+    /// Returns the input store at the given data index.
+    fn input_store(self, graph: &Graph, index: usize) -> Result<Store, GetStoreError> {
+        graph
+            .edges_directed(self.into(), Direction::Incoming)
+            .find(|edge| matches!(edge.weight(), GraphEdge::DataMap(i) if *i == index))
+            .map(|edge| Store(edge.source()))
+            .ok_or(GetStoreError::NoStore)
+    }
+    /// Returns the output store at the given data index.
+    fn output_store(self, graph: &Graph, index: usize) -> Result<Store, GetStoreError> {
+        graph
+            .edges_directed(self.into(), Direction::Outgoing)
+            .find(|edge| matches!(edge.weight(), GraphEdge::DataMap(i) if *i == index))
+            .map(|edge| Store(edge.target()))
+            .ok_or(GetStoreError::NoStore)
+    }
+
+    /// Returns the store of the input with the given name.
+    fn input_port(self, graph: &Graph, name: &str) -> Result<Store, GetStoreError> {
+        let index = graph
+            .node_weight(self.into())
+            .ok_or(GetStoreError::NoStore)?
+            .input_index(name)
+            .ok_or_else(|| GetStoreError::UnknownPort(name.to_string()))?;
+        self.input_store(graph, index)
+    }
+    /// Returns the store of the output with the given name.
+    fn output_port(self, graph: &Graph, name: &str) -> Result<Store, GetStoreError> {
+        let index = graph
+            .node_weight(self.into())
+            .ok_or(GetStoreError::NoStore)?
+            .output_index(name)
+            .ok_or_else(|| GetStoreError::UnknownPort(name.to_string()))?;
+        self.output_store(graph, index)
+    }
+    /// Returns the store of the port with the given name.
+    /// Inputs are checked before outputs.
+    fn port(self, graph: &Graph, name: &str) -> Result<Store, GetStoreError> {
+        self.input_port(graph, name)
+            .or_else(|_| self.output_port(graph, name))
+    }
+
     fn input_execution(self, graph: &Graph) -> impl Iterator<Item = NodeIndex> + '_ {
         graph
             .edges_directed(self.into(), Direction::Incoming)
-            .filter(|edge| matches!(edge.weight(), GraphEdge::ExecutionFlow))
+            .filter(|edge| matches!(edge.weight(), GraphEdge::ExecutionFlow(_)))
             .map(|edge| edge.source())
     }
     fn output_execution(self, graph: &Graph) -> impl Iterator<Item = NodeIndex> + '_ {
         graph
             .edges_directed(self.into(), Direction::Outgoing)
-            .filter(|edge| matches!(edge.weight(), GraphEdge::ExecutionFlow))
+            .filter(|edge| matches!(edge.weight(), GraphEdge::ExecutionFlow(_)))
             .map(|edge| edge.target())
     }
 
     /// Adds an execution flow from the given node to this node.
     fn run_after(self, graph: &mut Graph, node: NodeIndex) {
-        graph.add_edge(node, self.into(), GraphEdge::ExecutionFlow);
+        graph.add_edge(node, self.into(), GraphEdge::ExecutionFlow(0));
     }
 
     /// Adds an execution flow from this node to the given node.
     fn run_before(self, graph: &mut Graph, node: NodeIndex) {
-        graph.add_edge(self.into(), node, GraphEdge::ExecutionFlow);
+        self.run_before_output(graph, node, 0);
+    }
+
+    /// Adds an execution flow from the given execution output of this node to the given node.
+    fn run_before_output(self, graph: &mut Graph, node: NodeIndex, output: usize) {
+        graph.add_edge(self.into(), node, GraphEdge::ExecutionFlow(output));
+    }
+
+    /// Adds an error flow from this node to the given node.
+    /// When this node fails, execution continues from the given node instead of stopping.
+    fn on_error(self, graph: &mut Graph, node: NodeIndex) {
+        graph.add_edge(self.into(), node, GraphEdge::ErrorFlow);
+    }
+
+    /// Returns the store the error message is written to when this node fails,
+    /// adding it if needed.
+    fn error_store(self, graph: &mut Graph) -> Store {
+        let existing = graph
+            .edges_directed(self.into(), Direction::Outgoing)
+            .find(|edge| matches!(edge.weight(), GraphEdge::ErrorMap))
+            .map(|edge| Store(edge.target()));
+
+        existing.unwrap_or_else(|| {
+            let store = graph.add_node(GraphNode::Store(Value::String(Default::default())));
+            graph.add_edge(self.into(), store, GraphEdge::ErrorMap);
+            Store(store)
+        })
     }
 }
+
+impl Node for NodeIndex {}
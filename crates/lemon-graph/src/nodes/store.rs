@@ -1,12 +1,20 @@
 use petgraph::{graph::NodeIndex, visit::EdgeRef, Direction};
 use thiserror::Error;
 
-use crate::{Graph, GraphEdge, GraphNode, Value};
+use crate::{Graph, GraphEdge, GraphNode, Value, ValueKind};
 
 #[derive(Debug, Error)]
 pub enum GetStoreError {
     #[error("No store found")]
     NoStore,
+    #[error("Unknown port {0}")]
+    UnknownPort(String),
+}
+
+#[derive(Debug, Error)]
+pub enum ConnectError {
+    #[error("Cannot connect {from:?} output to {to:?} input")]
+    IncompatibleKinds { from: ValueKind, to: ValueKind },
 }
 
 /// Stores data, for transfer between nodes.
@@ -29,9 +37,44 @@ impl Store {
             .map(|edge| Store(edge.target()))
     }
 
+    /// Returns the kind of value this store holds, as declared by the node it belongs to.
+    pub fn kind(self, graph: &Graph) -> ValueKind {
+        for edge in graph.edges_directed(self.0, Direction::Outgoing) {
+            if let GraphEdge::DataMap(index) = edge.weight() {
+                return graph[edge.target()].input_kind(*index);
+            }
+        }
+
+        for edge in graph.edges_directed(self.0, Direction::Incoming) {
+            match edge.weight() {
+                GraphEdge::DataMap(index) => return graph[edge.source()].output_kind(*index),
+                GraphEdge::ErrorMap => return ValueKind::String,
+                _ => {}
+            }
+        }
+
+        ValueKind::Any
+    }
+
+    /// Checks that data can flow from one store to another.
+    fn check_kinds(graph: &Graph, from: Store, to: Store) -> Result<(), ConnectError> {
+        let (from, to) = (from.kind(graph), to.kind(graph));
+
+        if from.is_compatible(to) {
+            Ok(())
+        } else {
+            Err(ConnectError::IncompatibleKinds { from, to })
+        }
+    }
+
     /// Sets the input of the store.
     /// This will remove any existing inputs.
-    pub fn set_input(&self, graph: &mut Graph, store: Option<Store>) {
+    /// Fails if the kinds of the stores are incompatible, leaving existing inputs unchanged.
+    pub fn set_input(&self, graph: &mut Graph, store: Option<Store>) -> Result<(), ConnectError> {
+        if let Some(store) = store {
+            Self::check_kinds(graph, store, *self)?;
+        }
+
         // Remove any existing inputs.
         let edges = graph
             .edges_directed(self.0, Direction::Incoming)
@@ -47,11 +90,24 @@ impl Store {
         if let Some(store) = store {
             graph.add_edge(store.0, self.0, GraphEdge::DataFlow);
         }
+
+        Ok(())
     }
 
     /// Adds an output edge to the given store.
-    pub fn add_output(&self, graph: &mut Graph, store: Store) {
+    /// Fails if the kinds of the stores are incompatible.
+    pub fn add_output(&self, graph: &mut Graph, store: Store) -> Result<(), ConnectError> {
+        Self::check_kinds(graph, *self, store)?;
         graph.add_edge(self.0, store.0, GraphEdge::DataFlow);
+        Ok(())
+    }
+
+    /// Returns the current value of the store.
+    pub fn value(self, graph: &Graph) -> Option<&Value> {
+        match graph.node_weight(self.0) {
+            Some(GraphNode::Store(value)) => Some(value),
+            _ => None,
+        }
     }
 
     /// Sets the default value of the store.
@@ -60,3 +116,85 @@ impl Store {
         graph[self.0] = GraphNode::Store(value);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::nodes::{BranchNode, CallbackNode, Node, PromptNode};
+
+    use super::*;
+
+    #[test]
+    fn test_kind() {
+        let mut graph = Graph::default();
+
+        let prompt = PromptNode::new(&mut graph);
+        let branch = BranchNode::new(&mut graph);
+
+        assert_eq!(
+            prompt.output(&graph).unwrap().kind(&graph),
+            ValueKind::String
+        );
+        assert_eq!(
+            branch.condition(&graph).unwrap().kind(&graph),
+            ValueKind::Bool
+        );
+
+        let store = Store(graph.add_node(GraphNode::Store(Value::Bool(false))));
+        assert_eq!(store.kind(&graph), ValueKind::Any);
+    }
+
+    #[test]
+    fn test_incompatible_input() {
+        let mut graph = Graph::default();
+
+        let prompt = PromptNode::new(&mut graph);
+        let branch = BranchNode::new(&mut graph);
+
+        let output = prompt.output(&graph).unwrap();
+        let condition = branch.condition(&graph).unwrap();
+
+        let res = condition.set_input(&mut graph, Some(output));
+        assert!(matches!(
+            res,
+            Err(ConnectError::IncompatibleKinds {
+                from: ValueKind::String,
+                to: ValueKind::Bool
+            })
+        ));
+        assert_eq!(condition.inputs(&graph).count(), 0);
+
+        let res = output.add_output(&mut graph, condition);
+        assert!(res.is_err());
+        assert_eq!(output.outputs(&graph).count(), 0);
+    }
+
+    #[test]
+    fn test_any_input() {
+        let mut graph = Graph::default();
+
+        let callback = CallbackNode::new(&mut graph, |value| value);
+        let branch = BranchNode::new(&mut graph);
+
+        let output = callback.output(&graph).unwrap();
+        let condition = branch.condition(&graph).unwrap();
+
+        condition.set_input(&mut graph, Some(output)).unwrap();
+        assert_eq!(condition.inputs(&graph).count(), 1);
+    }
+
+    #[test]
+    fn test_stale_port() {
+        let mut graph = Graph::default();
+
+        let prompt = PromptNode::new(&mut graph);
+        assert!(matches!(
+            prompt.input_port(&graph, "missing"),
+            Err(GetStoreError::UnknownPort(_))
+        ));
+
+        // Ports of removed nodes are not found, rather than panicking.
+        graph.clear();
+        assert!(matches!(prompt.input(&graph), Err(GetStoreError::NoStore)));
+        assert!(matches!(prompt.output(&graph), Err(GetStoreError::NoStore)));
+    }
+}
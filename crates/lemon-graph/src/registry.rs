@@ -0,0 +1,225 @@
+use std::collections::BTreeMap;
+
+use petgraph::graph::NodeIndex;
+
+use crate::{
+    nodes::register_core, Graph, GraphEdge, GraphNode, LoadError, NodeData, Value, ValueKind,
+};
+
+type NodeFactory = Box<dyn Fn(Option<&Value>) -> Result<GraphNode, LoadError> + Send + Sync>;
+
+/// Input or output of a node type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Port {
+    pub name: String,
+    /// Kind of value the port expects or produces.
+    pub kind: ValueKind,
+    /// Initial value of the port's store.
+    pub value: Value,
+}
+
+impl Port {
+    pub fn new(name: impl Into<String>, kind: ValueKind, value: Value) -> Self {
+        Self {
+            name: name.into(),
+            kind,
+            value,
+        }
+    }
+}
+
+/// A registered node type, which can be created by name.
+pub struct NodeType {
+    pub name: String,
+    pub inputs: Vec<Port>,
+    pub outputs: Vec<Port>,
+    factory: NodeFactory,
+}
+
+impl NodeType {
+    pub fn new(
+        name: impl Into<String>,
+        inputs: Vec<Port>,
+        outputs: Vec<Port>,
+        factory: impl Fn(Option<&Value>) -> Result<GraphNode, LoadError> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            inputs,
+            outputs,
+            factory: Box::new(factory),
+        }
+    }
+}
+
+/// Registry of node types, used to create nodes from their [NodeData].
+pub struct NodeRegistry {
+    types: BTreeMap<String, NodeType>,
+}
+
+impl Default for NodeRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NodeRegistry {
+    /// Creates a registry containing the core nodes.
+    pub fn new() -> Self {
+        let mut registry = Self {
+            types: BTreeMap::new(),
+        };
+        register_core(&mut registry);
+        registry
+    }
+
+    /// Registers a node type, replacing any existing type with the same name.
+    pub fn register(&mut self, node_type: NodeType) {
+        self.types.insert(node_type.name.clone(), node_type);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&NodeType> {
+        self.types.get(name)
+    }
+
+    /// Returns an iterator over all registered node types, sorted by name.
+    pub fn types(&self) -> impl Iterator<Item = &NodeType> {
+        self.types.values()
+    }
+
+    /// Creates the weight of a node, without any stores.
+    /// Can be passed to [GraphData::load](crate::GraphData::load).
+    pub fn load(&self, data: &NodeData) -> Result<GraphNode, LoadError> {
+        let node_type = self
+            .get(&data.name)
+            .ok_or_else(|| LoadError::UnknownNode(data.name.clone()))?;
+
+        (node_type.factory)(data.config.as_ref())
+    }
+
+    /// Adds a node to the graph, along with a store for each of its ports.
+    ///
+    /// Nodes whose ports depend on their config, such as `lemon.join`, also get a
+    /// store for each port the type does not declare, starting as [Value::Null].
+    pub fn create(&self, graph: &mut Graph, data: &NodeData) -> Result<NodeIndex, LoadError> {
+        let weight = self.load(data)?;
+        let node_type = &self.types[&data.name];
+
+        let ports = weight.ports();
+        let inputs = ports.inputs.len().max(node_type.inputs.len());
+        let outputs = ports.outputs.len().max(node_type.outputs.len());
+        let value =
+            |ports: &[Port], i: usize| ports.get(i).map_or(Value::Null, |p| p.value.clone());
+
+        let index = graph.add_node(weight);
+
+        for i in 0..inputs {
+            let store = graph.add_node(GraphNode::Store(value(&node_type.inputs, i)));
+            graph.add_edge(store, index, GraphEdge::DataMap(i));
+        }
+
+        for i in 0..outputs {
+            let store = graph.add_node(GraphNode::Store(value(&node_type.outputs, i)));
+            graph.add_edge(index, store, GraphEdge::DataMap(i));
+        }
+
+        Ok(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_test::traced_test;
+
+    use crate::{
+        nodes::{LogNode, Node},
+        Executor,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_types() {
+        let registry = NodeRegistry::new();
+
+        let names = registry
+            .types()
+            .map(|t| t.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                "lemon.branch",
+                "lemon.for_each",
+                "lemon.join",
+                "lemon.log",
+                "lemon.prompt"
+            ]
+        );
+
+        let log = registry.get("lemon.log").unwrap();
+        assert_eq!(log.inputs.len(), 1);
+        assert!(log.outputs.is_empty());
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_create() {
+        let registry = NodeRegistry::new();
+        let mut graph = Graph::default();
+
+        let index = registry
+            .create(&mut graph, &NodeData::new("lemon.log"))
+            .unwrap();
+        let log = LogNode(index);
+
+        let message = log.message(&graph).unwrap();
+        message.set_value(&mut graph, "Hello, world!".to_string().into());
+
+        Executor::execute(&mut graph, log.0).await.unwrap();
+
+        assert!(logs_contain("Hello, world!"));
+        assert_eq!(log.output_stores(&graph).count(), 0);
+    }
+
+    #[test]
+    fn test_create_join() {
+        let registry = NodeRegistry::new();
+        let mut graph = Graph::default();
+
+        // A store is created for each branch in the config.
+        let data = NodeData {
+            name: "lemon.join".to_string(),
+            config: Some(Value::USize(3)),
+        };
+        let index = registry.create(&mut graph, &data).unwrap();
+
+        assert_eq!(index.input_stores(&graph).count(), 3);
+        assert_eq!(index.output_stores(&graph).count(), 3);
+        assert_eq!(graph[index].data(), Some(data));
+    }
+
+    #[test]
+    fn test_port_names() {
+        let registry = NodeRegistry::new();
+        let mut graph = Graph::default();
+
+        // Created nodes declare the same ports as their type.
+        for node_type in registry.types() {
+            let index = registry
+                .create(&mut graph, &NodeData::new(&node_type.name))
+                .unwrap();
+
+            for (i, port) in node_type.inputs.iter().enumerate() {
+                assert_eq!(graph[index].input_index(&port.name), Some(i));
+                assert_eq!(graph[index].input_kind(i), port.kind);
+                assert!(index.port(&graph, &port.name).is_ok());
+            }
+
+            for (i, port) in node_type.outputs.iter().enumerate() {
+                assert_eq!(graph[index].output_index(&port.name), Some(i));
+                assert_eq!(graph[index].output_kind(i), port.kind);
+            }
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use std::fmt::{Debug, Formatter};
+
+use futures_util::{stream, Stream, StreamExt};
+use thiserror::Error;
+use tokio::sync::watch;
+
+use crate::Value;
+
+/// Error read from a [ValueStream] whose producer failed.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("Stream failed: {0}")]
+pub struct StreamError(pub String);
+
+/// Chunks sent so far, and how the stream ended.
+#[derive(Default)]
+struct StreamState {
+    chunks: Vec<Value>,
+    end: Option<Result<(), StreamError>>,
+}
+
+/// Sending half of a [ValueStream].
+///
+/// The stream is ended with [close](Self::close) or [fail](Self::fail).
+/// Dropping the sender without closing it fails the stream, so readers can
+/// tell a producer that stopped early, such as a cancelled task, from one that finished.
+pub struct StreamSender(watch::Sender<StreamState>);
+
+impl StreamSender {
+    /// Sends a chunk to every reader of the stream.
+    pub fn send(&self, chunk: Value) {
+        self.0.send_modify(|state| state.chunks.push(chunk));
+    }
+
+    /// Ends the stream successfully.
+    pub fn close(self) {
+        self.end(Ok(()));
+    }
+
+    /// Ends the stream with an error, returned to readers after the chunks sent so far.
+    pub fn fail(self, error: impl ToString) {
+        self.end(Err(StreamError(error.to_string())));
+    }
+
+    fn end(&self, end: Result<(), StreamError>) {
+        self.0.send_modify(|state| {
+            state.end.get_or_insert(end);
+        });
+    }
+}
+
+impl Drop for StreamSender {
+    fn drop(&mut self) {
+        self.end(Err(StreamError(
+            "Stream was dropped before it was closed".to_string(),
+        )));
+    }
+}
+
+/// Stream of [Value] chunks, passed between nodes as a [Value::Stream].
+///
+/// Chunks are kept for the lifetime of the stream, so every reader sees every
+/// chunk from the start, no matter when it started reading.
+#[derive(Clone)]
+pub struct ValueStream(watch::Receiver<StreamState>);
+
+impl ValueStream {
+    /// Creates a new stream, along with the sender used to write to it.
+    pub fn channel() -> (StreamSender, Self) {
+        let (sender, receiver) = watch::channel(StreamState::default());
+        (StreamSender(sender), Self(receiver))
+    }
+
+    /// Creates a closed stream containing the given chunks.
+    pub fn from_chunks(chunks: Vec<Value>) -> Self {
+        let (_, receiver) = watch::channel(StreamState {
+            chunks,
+            end: Some(Ok(())),
+        });
+        Self(receiver)
+    }
+
+    /// Returns a reader that yields each chunk as it arrives,
+    /// and ends once the stream is closed.
+    /// If the stream failed, the error is yielded after the last chunk.
+    pub fn reader(&self) -> impl Stream<Item = Result<Value, StreamError>> + Send + 'static {
+        stream::unfold(Some((self.0.clone(), 0)), |reader| async move {
+            let (mut receiver, index) = reader?;
+
+            loop {
+                let next = {
+                    let state = receiver.borrow_and_update();
+
+                    match (state.chunks.get(index), &state.end) {
+                        (Some(chunk), _) => Some(Ok(chunk.clone())),
+                        (None, Some(Err(e))) => Some(Err(e.clone())),
+                        (None, Some(Ok(()))) => return None,
+                        (None, None) => None,
+                    }
+                };
+
+                match next {
+                    Some(Ok(chunk)) => return Some((Ok(chunk), Some((receiver, index + 1)))),
+                    Some(Err(e)) => return Some((Err(e), None)),
+                    // The sender always ends the stream before it is dropped,
+                    // so waiting for the next update cannot miss the end.
+                    None => {
+                        let _ = receiver.changed().await;
+                    }
+                }
+            }
+        })
+    }
+
+    /// Waits for the stream to close, and returns every chunk.
+    pub async fn collect(&self) -> Result<Vec<Value>, StreamError> {
+        self.reader()
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect()
+    }
+
+    /// Waits for the stream to close, and joins every chunk into a string.
+    pub async fn collect_string(&self) -> Result<String, StreamError> {
+        let mut text = String::new();
+
+        for chunk in self.collect().await? {
+            match chunk {
+                Value::String(chunk) => text.push_str(&chunk),
+                chunk => text.push_str(&chunk.to_string()),
+            }
+        }
+
+        Ok(text)
+    }
+}
+
+impl Debug for ValueStream {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ValueStream")
+            .field(&self.0.borrow().chunks.len())
+            .finish()
+    }
+}
+
+/// Streams are equal if they read from the same channel.
+impl PartialEq for ValueStream {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.same_channel(&other.0)
+    }
+}
+
+impl PartialOrd for ValueStream {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        (self == other).then_some(std::cmp::Ordering::Equal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stream() {
+        let (sender, stream) = ValueStream::channel();
+
+        let reader = tokio::spawn({
+            let stream = stream.clone();
+            async move { stream.collect_string().await }
+        });
+
+        for chunk in ["Hello", ", ", "world"] {
+            sender.send(Value::String(chunk.to_string()));
+            tokio::task::yield_now().await;
+        }
+        sender.close();
+
+        assert_eq!(reader.await.unwrap().unwrap(), "Hello, world");
+
+        // Late readers still see every chunk.
+        assert_eq!(stream.collect().await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_fail() {
+        let (sender, stream) = ValueStream::channel();
+        sender.send(Value::USize(1));
+        sender.fail("Connection lost");
+
+        // Chunks sent before the failure are read first.
+        let chunks: Vec<_> = stream.reader().collect().await;
+        assert_eq!(
+            chunks,
+            vec![
+                Ok(Value::USize(1)),
+                Err(StreamError("Connection lost".to_string()))
+            ]
+        );
+        assert!(stream.collect_string().await.is_err());
+
+        // Dropping the sender without closing it fails the stream.
+        let (sender, stream) = ValueStream::channel();
+        drop(sender);
+        assert!(stream.collect().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_from_chunks() {
+        let stream = ValueStream::from_chunks(vec![Value::USize(1), Value::USize(2)]);
+
+        assert_eq!(
+            stream.collect().await,
+            Ok(vec![Value::USize(1), Value::USize(2)])
+        );
+        assert_eq!(stream.collect_string().await, Ok("12".to_string()));
+    }
+
+    #[test]
+    fn test_eq() {
+        let (_, stream) = ValueStream::channel();
+        let (_, other) = ValueStream::channel();
+
+        assert_eq!(stream, stream.clone());
+        assert_ne!(stream, other);
+    }
+}
@@ -0,0 +1,337 @@
+use std::collections::BTreeMap;
+
+use petgraph::{
+    algo::tarjan_scc,
+    graph::{EdgeIndex, NodeIndex},
+    visit::{Bfs, EdgeFiltered, EdgeRef},
+    Direction,
+};
+use thiserror::Error;
+
+use crate::{nodes::Store, Graph, GraphEdge, GraphNode, ValueKind};
+
+/// Problem found when validating a graph.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum Diagnostic {
+    #[error("Start node {0:?} is not executable")]
+    InvalidStart(NodeIndex),
+    #[error("Execution flow edge {0:?} does not connect two executable nodes")]
+    InvalidExecutionFlow(EdgeIndex),
+    #[error("Data flow edge {0:?} does not connect two stores")]
+    InvalidDataFlow(EdgeIndex),
+    #[error("Data flow edge {edge:?} connects {from:?} to {to:?}")]
+    IncompatibleKinds {
+        edge: EdgeIndex,
+        from: ValueKind,
+        to: ValueKind,
+    },
+    #[error("Data map edge {0:?} does not connect a store and an executable node")]
+    InvalidDataMap(EdgeIndex),
+    #[error("Error map edge {0:?} does not connect an executable node to a store")]
+    InvalidErrorMap(EdgeIndex),
+    #[error("Node {node:?} has no input at index {index}")]
+    MissingInput { node: NodeIndex, index: usize },
+    #[error("Node {node:?} has multiple inputs at index {index}")]
+    DuplicateInput { node: NodeIndex, index: usize },
+    #[error("Node {node:?} has multiple outputs at index {index}")]
+    DuplicateOutput { node: NodeIndex, index: usize },
+    #[error("Stores {0:?} form a data flow cycle")]
+    DataFlowCycle(Vec<NodeIndex>),
+    #[error("Node {0:?} cannot be reached from the start node")]
+    Unreachable(NodeIndex),
+    #[error("Node {0:?} is marked as pure, but cannot be cached")]
+    Uncacheable(NodeIndex),
+}
+
+/// Validates a graph before executing it from the given start node.
+/// Returns a list of problems found, which is empty if the graph is valid.
+pub fn validate(graph: &Graph, start: NodeIndex) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    let is_executable = |idx: NodeIndex| {
+        graph
+            .node_weight(idx)
+            .is_some_and(|node| !matches!(node, GraphNode::Store(_)))
+    };
+    let is_store = |idx: NodeIndex| matches!(graph.node_weight(idx), Some(GraphNode::Store(_)));
+
+    if !is_executable(start) {
+        diagnostics.push(Diagnostic::InvalidStart(start));
+    }
+
+    // Check that edges connect the right kinds of nodes.
+    for edge in graph.edge_references() {
+        let (source, target) = (edge.source(), edge.target());
+
+        let diagnostic = match edge.weight() {
+            GraphEdge::ExecutionFlow(_) | GraphEdge::ErrorFlow
+                if !(is_executable(source) && is_executable(target)) =>
+            {
+                Diagnostic::InvalidExecutionFlow(edge.id())
+            }
+            GraphEdge::DataFlow if !(is_store(source) && is_store(target)) => {
+                Diagnostic::InvalidDataFlow(edge.id())
+            }
+            GraphEdge::DataFlow => {
+                let from = Store(source).kind(graph);
+                let to = Store(target).kind(graph);
+
+                if from.is_compatible(to) {
+                    continue;
+                }
+
+                Diagnostic::IncompatibleKinds {
+                    edge: edge.id(),
+                    from,
+                    to,
+                }
+            }
+            GraphEdge::DataMap(_)
+                if !(is_store(source) && is_executable(target)
+                    || is_executable(source) && is_store(target)) =>
+            {
+                Diagnostic::InvalidDataMap(edge.id())
+            }
+            GraphEdge::ErrorMap if !(is_executable(source) && is_store(target)) => {
+                Diagnostic::InvalidErrorMap(edge.id())
+            }
+            _ => continue,
+        };
+
+        diagnostics.push(diagnostic);
+    }
+
+    // Check that data indices of each node are unique, and inputs are contiguous.
+    for node in graph.node_indices().filter(|idx| is_executable(*idx)) {
+        for direction in [Direction::Incoming, Direction::Outgoing] {
+            let mut counts = BTreeMap::new();
+
+            for edge in graph.edges_directed(node, direction) {
+                if let GraphEdge::DataMap(index) = edge.weight() {
+                    *counts.entry(*index).or_insert(0) += 1;
+                }
+            }
+
+            for (index, count) in &counts {
+                if *count > 1 {
+                    diagnostics.push(match direction {
+                        Direction::Incoming => Diagnostic::DuplicateInput {
+                            node,
+                            index: *index,
+                        },
+                        Direction::Outgoing => Diagnostic::DuplicateOutput {
+                            node,
+                            index: *index,
+                        },
+                    });
+                }
+            }
+
+            // Inputs are passed to nodes by position, so a gap shifts every later input.
+            if direction == Direction::Incoming {
+                let max = counts.keys().next_back().copied();
+
+                for index in (0..max.map_or(0, |max| max + 1)).filter(|i| !counts.contains_key(i)) {
+                    diagnostics.push(Diagnostic::MissingInput { node, index });
+                }
+            }
+        }
+    }
+
+    // Check for data flow cycles between stores.
+    let data_flow =
+        EdgeFiltered::from_fn(graph, |edge| matches!(edge.weight(), GraphEdge::DataFlow));
+
+    for mut component in tarjan_scc(&data_flow) {
+        let cyclic = component.len() > 1
+            || graph
+                .edges_connecting(component[0], component[0])
+                .any(|edge| matches!(edge.weight(), GraphEdge::DataFlow));
+
+        if cyclic {
+            component.sort();
+            diagnostics.push(Diagnostic::DataFlowCycle(component));
+        }
+    }
+
+    // Check that every executable node can be reached from the start.
+    if is_executable(start) {
+        let execution_flow = EdgeFiltered::from_fn(graph, |edge| {
+            matches!(
+                edge.weight(),
+                GraphEdge::ExecutionFlow(_) | GraphEdge::ErrorFlow
+            )
+        });
+
+        let mut reachable = vec![false; graph.node_count()];
+        let mut bfs = Bfs::new(&execution_flow, start);
+
+        while let Some(idx) = bfs.next(&execution_flow) {
+            reachable[idx.index()] = true;
+        }
+
+        for node in graph.node_indices() {
+            if is_executable(node) && !reachable[node.index()] {
+                diagnostics.push(Diagnostic::Unreachable(node));
+            }
+        }
+    }
+
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        nodes::{BranchNode, CallbackNode, LogNode, Node, PromptNode},
+        ExecutionStepError, Executor, Value,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_valid() {
+        let mut graph = Graph::default();
+
+        let callback = CallbackNode::new(&mut graph, |value| value);
+        let log = LogNode::new(&mut graph);
+        log.run_after(&mut graph, callback.0);
+
+        let output = callback.output(&graph).unwrap();
+        let message = log.message(&graph).unwrap();
+        message.set_input(&mut graph, Some(output)).unwrap();
+
+        assert!(validate(&graph, callback.0).is_empty());
+    }
+
+    #[test]
+    fn test_invalid_edges() {
+        let mut graph = Graph::default();
+
+        let log = LogNode::new(&mut graph);
+        let other = LogNode::new(&mut graph);
+        log.run_before(&mut graph, other.0);
+
+        let message = log.message(&graph).unwrap();
+        let flow = graph.add_edge(log.0, message.0, GraphEdge::DataFlow);
+        let map = graph.add_edge(log.0, other.0, GraphEdge::DataMap(1));
+        let execution = graph.add_edge(message.0, log.0, GraphEdge::ExecutionFlow(0));
+        let error_flow = graph.add_edge(log.0, message.0, GraphEdge::ErrorFlow);
+        let error_map = graph.add_edge(log.0, other.0, GraphEdge::ErrorMap);
+
+        let diagnostics = validate(&graph, message.0);
+
+        assert!(diagnostics.contains(&Diagnostic::InvalidStart(message.0)));
+        assert!(diagnostics.contains(&Diagnostic::InvalidDataFlow(flow)));
+        assert!(diagnostics.contains(&Diagnostic::InvalidDataMap(map)));
+        assert!(diagnostics.contains(&Diagnostic::InvalidExecutionFlow(execution)));
+        assert!(diagnostics.contains(&Diagnostic::InvalidExecutionFlow(error_flow)));
+        assert!(diagnostics.contains(&Diagnostic::InvalidErrorMap(error_map)));
+    }
+
+    #[test]
+    fn test_incompatible_kinds() {
+        let mut graph = Graph::default();
+
+        let prompt = PromptNode::new(&mut graph);
+        let branch = BranchNode::new(&mut graph);
+        prompt.run_before(&mut graph, branch.0);
+
+        // Bypass the check in Store::set_input.
+        let output = prompt.output(&graph).unwrap();
+        let condition = branch.condition(&graph).unwrap();
+        let edge = graph.add_edge(output.0, condition.0, GraphEdge::DataFlow);
+
+        assert_eq!(
+            validate(&graph, prompt.0),
+            vec![Diagnostic::IncompatibleKinds {
+                edge,
+                from: ValueKind::String,
+                to: ValueKind::Bool,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_data_indices() {
+        let mut graph = Graph::default();
+
+        let log = LogNode::new(&mut graph);
+
+        let store = graph.add_node(GraphNode::Store(Value::Bool(false)));
+        graph.add_edge(store, log.0, GraphEdge::DataMap(2));
+
+        let store = graph.add_node(GraphNode::Store(Value::Bool(false)));
+        graph.add_edge(store, log.0, GraphEdge::DataMap(2));
+
+        let diagnostics = validate(&graph, log.0);
+
+        assert_eq!(
+            diagnostics,
+            vec![
+                Diagnostic::DuplicateInput {
+                    node: log.0,
+                    index: 2
+                },
+                Diagnostic::MissingInput {
+                    node: log.0,
+                    index: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_data_flow_cycle() {
+        let mut graph = Graph::default();
+
+        let log = LogNode::new(&mut graph);
+        let message = log.message(&graph).unwrap();
+
+        let store = Store(graph.add_node(GraphNode::Store(Value::Bool(false))));
+        message.set_input(&mut graph, Some(store)).unwrap();
+        store.set_input(&mut graph, Some(message)).unwrap();
+
+        let diagnostics = validate(&graph, log.0);
+
+        let mut cycle = vec![message.0, store.0];
+        cycle.sort();
+        assert_eq!(diagnostics, vec![Diagnostic::DataFlowCycle(cycle)]);
+    }
+
+    #[test]
+    fn test_unreachable() {
+        let mut graph = Graph::default();
+
+        let log = LogNode::new(&mut graph);
+        let next = LogNode::new(&mut graph);
+        log.run_before(&mut graph, next.0);
+        let handler = LogNode::new(&mut graph);
+        next.on_error(&mut graph, handler.0);
+        let other = LogNode::new(&mut graph);
+
+        let diagnostics = validate(&graph, log.0);
+
+        assert_eq!(diagnostics, vec![Diagnostic::Unreachable(other.0)]);
+    }
+
+    #[tokio::test]
+    async fn test_executor_validate() {
+        let mut graph = Graph::default();
+
+        let log = LogNode::new(&mut graph);
+        let other = LogNode::new(&mut graph);
+
+        let executor = Executor {
+            validate: true,
+            ..Default::default()
+        };
+        let res = executor.run(&mut graph, log.0).await;
+
+        assert!(matches!(
+            res,
+            Err(ExecutionStepError::InvalidGraph(diagnostics))
+                if diagnostics == vec![Diagnostic::Unreachable(other.0)]
+        ));
+    }
+}
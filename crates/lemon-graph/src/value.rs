@@ -1,26 +1,155 @@
-use std::fmt::{Display, Formatter};
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+    hash::{Hash, Hasher},
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::ValueStream;
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Value {
     Bool(bool),
     Bytes(Vec<u8>),
     F32(f32),
+    F64(f64),
+    I64(i64),
     ISize(isize),
+    Map(BTreeMap<String, Value>),
+    Null,
+    /// Chunks produced over time, such as the tokens of an LLM response.
+    /// Streams cannot be serialized, so saving a graph or [Trace](crate::Trace)
+    /// holding one fails, and their outputs are never cached.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Stream(ValueStream),
     String(String),
+    U64(u64),
     USize(usize),
     Vec(Vec<Value>),
 }
 
+impl Value {
+    pub fn kind(&self) -> ValueKind {
+        match self {
+            Value::Bool(_) => ValueKind::Bool,
+            Value::Bytes(_) => ValueKind::Bytes,
+            Value::F32(_) => ValueKind::F32,
+            Value::F64(_) => ValueKind::F64,
+            Value::I64(_) => ValueKind::I64,
+            Value::ISize(_) => ValueKind::ISize,
+            Value::Map(_) => ValueKind::Map,
+            Value::Null => ValueKind::Null,
+            Value::Stream(_) => ValueKind::Stream,
+            Value::String(_) => ValueKind::String,
+            Value::U64(_) => ValueKind::U64,
+            Value::USize(_) => ValueKind::USize,
+            Value::Vec(_) => ValueKind::Vec,
+        }
+    }
+}
+
+/// Kind of [Value] expected by a port.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ValueKind {
+    /// Accepts any kind of value.
+    #[default]
+    Any,
+    Bool,
+    Bytes,
+    F32,
+    F64,
+    I64,
+    ISize,
+    Map,
+    Null,
+    Stream,
+    String,
+    U64,
+    USize,
+    Vec,
+}
+
+impl ValueKind {
+    /// Returns whether values of this kind can be passed to a port of the other kind.
+    pub fn is_compatible(self, other: ValueKind) -> bool {
+        self == ValueKind::Any || other == ValueKind::Any || self == other
+    }
+}
+
+/// Floats are hashed by their bits, with `-0.0` hashed as `0.0` as they are equal.
+/// Streams are hashed by kind only, as their contents may not be known yet.
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.kind() as u8).hash(state);
+
+        match self {
+            Value::Bool(value) => value.hash(state),
+            Value::Bytes(value) => value.hash(state),
+            // Adding 0.0 turns -0.0 into 0.0, and leaves other values unchanged.
+            Value::F32(value) => (value + 0.0).to_bits().hash(state),
+            Value::F64(value) => (value + 0.0).to_bits().hash(state),
+            Value::I64(value) => value.hash(state),
+            Value::ISize(value) => value.hash(state),
+            Value::Map(value) => value.hash(state),
+            Value::Null | Value::Stream(_) => {}
+            Value::String(value) => value.hash(state),
+            Value::U64(value) => value.hash(state),
+            Value::USize(value) => value.hash(state),
+            Value::Vec(value) => value.hash(state),
+        }
+    }
+}
+
 impl Display for Value {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Value::Bool(value) => write!(f, "{}", value),
             Value::Bytes(value) => write!(f, "{:?}", value),
             Value::F32(value) => write!(f, "{}", value),
+            Value::F64(value) => write!(f, "{}", value),
+            Value::I64(value) => write!(f, "{}", value),
             Value::ISize(value) => write!(f, "{}", value),
+            Value::Map(value) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in value.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:?}: ", key)?;
+                    value.fmt_nested(f)?;
+                }
+                write!(f, "}}")
+            }
+            Value::Null => write!(f, "null"),
+            Value::Stream(_) => write!(f, "<stream>"),
             Value::String(value) => write!(f, "{}", value),
+            Value::U64(value) => write!(f, "{}", value),
             Value::USize(value) => write!(f, "{}", value),
-            Value::Vec(value) => write!(f, "{:?}", value),
+            Value::Vec(value) => {
+                write!(f, "[")?;
+                for (i, value) in value.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    value.fmt_nested(f)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+impl Value {
+    /// Formats a value inside a [Value::Vec] or [Value::Map].
+    /// Strings are quoted, so they can be told apart from other values.
+    fn fmt_nested(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::String(value) => write!(f, "{:?}", value),
+            value => write!(f, "{}", value),
         }
     }
 }
@@ -43,18 +172,48 @@ impl From<f32> for Value {
     }
 }
 
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::F64(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::I64(value)
+    }
+}
+
 impl From<isize> for Value {
     fn from(value: isize) -> Self {
         Value::ISize(value)
     }
 }
 
+impl From<BTreeMap<String, Value>> for Value {
+    fn from(value: BTreeMap<String, Value>) -> Self {
+        Value::Map(value)
+    }
+}
+
+impl From<()> for Value {
+    fn from(_: ()) -> Self {
+        Value::Null
+    }
+}
+
 impl From<String> for Value {
     fn from(value: String) -> Self {
         Value::String(value)
     }
 }
 
+impl From<u64> for Value {
+    fn from(value: u64) -> Self {
+        Value::U64(value)
+    }
+}
+
 impl From<usize> for Value {
     fn from(value: usize) -> Self {
         Value::USize(value)
@@ -100,6 +259,28 @@ impl TryFrom<Value> for f32 {
     }
 }
 
+impl TryFrom<Value> for f64 {
+    type Error = ();
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::F64(value) => Ok(value),
+            _ => Err(()),
+        }
+    }
+}
+
+impl TryFrom<Value> for i64 {
+    type Error = ();
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::I64(value) => Ok(value),
+            _ => Err(()),
+        }
+    }
+}
+
 impl TryFrom<Value> for isize {
     type Error = ();
 
@@ -111,6 +292,17 @@ impl TryFrom<Value> for isize {
     }
 }
 
+impl TryFrom<Value> for BTreeMap<String, Value> {
+    type Error = ();
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Map(value) => Ok(value),
+            _ => Err(()),
+        }
+    }
+}
+
 impl TryFrom<Value> for String {
     type Error = ();
 
@@ -122,6 +314,17 @@ impl TryFrom<Value> for String {
     }
 }
 
+impl TryFrom<Value> for u64 {
+    type Error = ();
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::U64(value) => Ok(value),
+            _ => Err(()),
+        }
+    }
+}
+
 impl TryFrom<Value> for usize {
     type Error = ();
 
@@ -143,3 +346,159 @@ impl TryFrom<Value> for Vec<Value> {
         }
     }
 }
+
+/// Converts from JSON.
+/// Numbers become [Value::U64] if they fit, then [Value::I64], then [Value::F64].
+/// Converting the result back to JSON gives the original value.
+#[cfg(feature = "json")]
+impl From<serde_json::Value> for Value {
+    fn from(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => Value::Null,
+            serde_json::Value::Bool(value) => Value::Bool(value),
+            serde_json::Value::Number(value) => {
+                if let Some(value) = value.as_u64() {
+                    Value::U64(value)
+                } else if let Some(value) = value.as_i64() {
+                    Value::I64(value)
+                } else {
+                    Value::F64(value.as_f64().unwrap_or(f64::NAN))
+                }
+            }
+            serde_json::Value::String(value) => Value::String(value),
+            serde_json::Value::Array(value) => {
+                Value::Vec(value.into_iter().map(Value::from).collect())
+            }
+            serde_json::Value::Object(value) => Value::Map(
+                value
+                    .into_iter()
+                    .map(|(key, value)| (key, Value::from(value)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Converts to JSON.
+/// [Value::Bytes] become arrays of numbers.
+/// Fails if the value contains a non-finite float or a stream.
+///
+/// This is lossy: converting the result back gives a [Value::Vec] for bytes,
+/// [Value::F64] for floats and [Value::U64] or [Value::I64] for integers.
+#[cfg(feature = "json")]
+impl TryFrom<Value> for serde_json::Value {
+    type Error = ();
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        let float = |value: f64| {
+            serde_json::Number::from_f64(value)
+                .map(serde_json::Value::Number)
+                .ok_or(())
+        };
+
+        Ok(match value {
+            Value::Bool(value) => serde_json::Value::Bool(value),
+            Value::Bytes(value) => {
+                serde_json::Value::Array(value.into_iter().map(serde_json::Value::from).collect())
+            }
+            Value::F32(value) => float(value.into())?,
+            Value::F64(value) => float(value)?,
+            Value::I64(value) => serde_json::Value::from(value),
+            Value::ISize(value) => serde_json::Value::from(value),
+            Value::Map(value) => serde_json::Value::Object(
+                value
+                    .into_iter()
+                    .map(|(key, value)| Ok((key, value.try_into()?)))
+                    .collect::<Result<_, ()>>()?,
+            ),
+            Value::Null => serde_json::Value::Null,
+            Value::Stream(_) => return Err(()),
+            Value::String(value) => serde_json::Value::String(value),
+            Value::U64(value) => serde_json::Value::from(value),
+            Value::USize(value) => serde_json::Value::from(value),
+            Value::Vec(value) => serde_json::Value::Array(
+                value
+                    .into_iter()
+                    .map(serde_json::Value::try_from)
+                    .collect::<Result<_, _>>()?,
+            ),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let value = Value::Map(BTreeMap::from([
+            ("name".to_string(), Value::String("lemon".to_string())),
+            (
+                "tags".to_string(),
+                Value::Vec(vec![
+                    Value::String("a".to_string()),
+                    Value::USize(1),
+                    Value::Null,
+                ]),
+            ),
+        ]));
+
+        assert_eq!(
+            value.to_string(),
+            r#"{"name": "lemon", "tags": ["a", 1, null]}"#
+        );
+        assert_eq!(Value::String("lemon".to_string()).to_string(), "lemon");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_json() {
+        let json = serde_json::json!({
+            "bool": true,
+            "float": 1.5,
+            "negative": -1,
+            "null": null,
+            "positive": u64::MAX,
+            "list": ["a", { "nested": [] }],
+        });
+
+        let value = Value::from(json.clone());
+
+        match &value {
+            Value::Map(map) => {
+                assert_eq!(map["float"], Value::F64(1.5));
+                assert_eq!(map["negative"], Value::I64(-1));
+                assert_eq!(map["positive"], Value::U64(u64::MAX));
+            }
+            _ => panic!("Expected map"),
+        }
+
+        assert_eq!(serde_json::Value::try_from(value).unwrap(), json);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_json_lossy() {
+        let convert = |value: Value| Value::from(serde_json::Value::try_from(value).unwrap());
+
+        assert_eq!(convert(Value::I64(1)), Value::U64(1));
+        assert_eq!(convert(Value::I64(-1)), Value::I64(-1));
+        assert_eq!(convert(Value::USize(1)), Value::U64(1));
+        assert_eq!(convert(Value::F32(1.5)), Value::F64(1.5));
+        assert_eq!(
+            convert(Value::Bytes(vec![1, 2])),
+            Value::Vec(vec![Value::U64(1), Value::U64(2)])
+        );
+        assert_eq!(
+            convert(Value::String("lemon".to_string())),
+            Value::String("lemon".to_string())
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_json_non_finite() {
+        assert!(serde_json::Value::try_from(Value::F64(f64::NAN)).is_err());
+    }
+}
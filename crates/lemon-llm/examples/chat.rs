@@ -1,14 +1,13 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use lemon_graph::{
-    nodes::{CallbackNode, Node, PromptNode},
-    Executor, Value,
+    nodes::{CallbackNode, PromptNode},
+    Executor, GraphBuilder, RetryPolicy, Value,
 };
 use lemon_llm::{
     ollama::{OllamaBackend, OllamaModel},
     LlmNode,
 };
-use petgraph::Graph;
 
 #[tokio::main]
 async fn main() {
@@ -16,50 +15,41 @@ async fn main() {
         .with_max_level(tracing::Level::INFO)
         .init();
 
-    let mut graph = Graph::default();
-
-    // Create an LLM node.
     let backend = Arc::new(OllamaBackend {
         model: OllamaModel::Mistral,
         ..Default::default()
     });
-    let llm = LlmNode::from_backend(&mut graph, backend);
-
-    // Create a prompt node to get user input.
-    let prompt = PromptNode::new(&mut graph);
-
-    // Create a callback node to format the LLM output.
-    let format = CallbackNode::new(&mut graph, |input| {
-        let input = match input {
-            Value::String(value) => value,
-            _ => panic!("Invalid input"),
-        };
-
-        let out = format!("\n> {}\n", input);
-
-        Value::String(out)
-    });
-
-    // Connect the LLM output -> format input.
-    let format_input = format.input(&graph).unwrap();
-    let llm_output = llm.output(&graph).unwrap();
-    format_input.set_input(&mut graph, Some(llm_output));
-
-    // Connect the formatted output -> prompt input.
-    let prompt_input = prompt.input(&graph).unwrap();
-    let format_output = format.output(&graph).unwrap();
-    prompt_input.set_input(&mut graph, Some(format_output));
-
-    // Connect the prompt output -> LLM input.
-    let prompt_output = prompt.output(&graph).unwrap();
-    let llm_input = llm.input(&graph).unwrap();
-    llm_input.set_input(&mut graph, Some(prompt_output));
 
-    // Set the execution flow.
-    llm.run_after(&mut graph, prompt.0);
-    format.run_after(&mut graph, llm.0);
-    prompt.run_after(&mut graph, format.0);
+    let mut built = GraphBuilder::new()
+        // Create a prompt node to get user input.
+        .add("prompt", PromptNode::new)
+        // Create an LLM node.
+        .add("llm", |graph| LlmNode::from_backend(graph, backend))
+        // Create a callback node to format the LLM output.
+        .add("format", |graph| {
+            CallbackNode::new(graph, |input| {
+                let input = match input {
+                    Value::String(value) => value,
+                    _ => panic!("Invalid input"),
+                };
+
+                Value::String(format!("\n> {}\n", input))
+            })
+        })
+        // Prompt -> LLM -> format, then prompt again.
+        .chain(["prompt", "llm", "format", "prompt"])
+        .connect("prompt.output", "llm.prompt")
+        .connect("llm.response", "format.input")
+        .connect("format.output", "prompt.input")
+        .build()
+        .unwrap();
+
+    // Give up on the LLM if it takes too long to respond.
+    let mut executor = Executor::default();
+    executor.node(built["llm"]).timeout = Some(Duration::from_secs(120));
+    executor.node(built["llm"]).retry = Some(RetryPolicy::default());
 
     // Execute the graph.
-    Executor::execute(&mut graph, prompt.0).await.unwrap();
+    let prompt = built["prompt"];
+    executor.run(&mut built.graph, prompt).await.unwrap();
 }
@@ -3,12 +3,15 @@
 use std::{future::Future, sync::Arc};
 
 use lemon_graph::{
-    nodes::{AsyncNode, GetStoreError, Node, NodeError, Store},
-    Graph, GraphEdge, GraphNode, Value,
+    async_trait,
+    nodes::{AsyncNode, GetStoreError, Node, NodeError, NodePort, NodePorts, Store},
+    ExecutionContext, Graph, GraphEdge, GraphNode, NodeData, StreamSender, Value, ValueKind,
+    ValueStream,
 };
+#[cfg(feature = "ollama")]
+use lemon_graph::{LoadError, NodeRegistry, NodeType, Port};
 use petgraph::graph::NodeIndex;
 use thiserror::Error;
-use tracing::error;
 
 #[cfg(feature = "ollama")]
 pub mod ollama;
@@ -28,12 +31,13 @@ impl Node for LlmNode {}
 
 impl LlmNode {
     pub fn new<T: LlmBackend>(graph: &mut Graph, weight: LlmWeight<T>) -> Self {
-        let index = graph.add_node(GraphNode::AsyncNode(Box::new(weight)));
+        let response = weight.empty_response();
+        let index = graph.add_node(GraphNode::AsyncNode(Arc::new(weight)));
 
         let input = graph.add_node(GraphNode::Store(Value::String(Default::default())));
         graph.add_edge(input, index, GraphEdge::DataMap(0));
 
-        let output = graph.add_node(GraphNode::Store(Value::String(Default::default())));
+        let output = graph.add_node(GraphNode::Store(response));
         graph.add_edge(index, output, GraphEdge::DataMap(0));
 
         Self(index)
@@ -43,16 +47,17 @@ impl LlmNode {
         Self::new(graph, LlmWeight::new(backend))
     }
 
+    /// Creates a node that outputs the response as a [Value::Stream] of text chunks.
+    pub fn streaming<T: LlmBackend + 'static>(graph: &mut Graph, backend: Arc<T>) -> Self {
+        Self::new(graph, LlmWeight::streaming(backend))
+    }
+
     pub fn input(&self, graph: &Graph) -> Result<Store, GetStoreError> {
-        self.input_stores(graph)
-            .next()
-            .ok_or(GetStoreError::NoStore)
+        self.input_port(graph, "prompt")
     }
 
     pub fn output(&self, graph: &Graph) -> Result<Store, GetStoreError> {
-        self.output_stores(graph)
-            .next()
-            .ok_or(GetStoreError::NoStore)
+        self.output_port(graph, "response")
     }
 }
 
@@ -62,40 +67,336 @@ pub enum GenerateError {
     BackendError(String),
 }
 
-pub trait LlmBackend {
-    fn generate(&self, prompt: &str) -> impl Future<Output = Result<String, GenerateError>>;
+pub trait LlmBackend: Send + Sync {
+    fn generate(&self, prompt: &str) -> impl Future<Output = Result<String, GenerateError>> + Send;
+
+    /// Generates a response, sending each chunk of text to the sender as it arrives.
+    /// By default, the whole response is sent as a single chunk.
+    ///
+    /// The caller closes the stream once this returns, or fails it with the returned error.
+    fn generate_stream(
+        &self,
+        prompt: &str,
+        sender: &StreamSender,
+    ) -> impl Future<Output = Result<(), GenerateError>> + Send {
+        async move {
+            let response = self.generate(prompt).await?;
+            sender.send(Value::String(response));
+            Ok(())
+        }
+    }
+
+    /// Returns the config used to load an [LlmNode] with this backend.
+    /// Backends that cannot be loaded return `None`.
+    fn config(&self) -> Option<Value> {
+        None
+    }
 }
 
 pub struct LlmWeight<T: LlmBackend + 'static> {
     pub backend: Arc<T>,
+    /// Whether to output a [Value::Stream] instead of waiting for the whole response.
+    pub stream: bool,
 }
 
 impl<T: LlmBackend> LlmWeight<T> {
+    pub const NAME: &'static str = "lemon_llm.llm";
+    pub const STREAM_NAME: &'static str = "lemon_llm.llm_stream";
+
     pub fn new(backend: Arc<T>) -> Self {
-        Self { backend }
+        Self {
+            backend,
+            stream: false,
+        }
+    }
+
+    pub fn streaming(backend: Arc<T>) -> Self {
+        Self {
+            backend,
+            stream: true,
+        }
+    }
+
+    /// Returns the initial value of the response store.
+    /// Streams cannot be saved, so a streaming response starts as [Value::Null].
+    fn empty_response(&self) -> Value {
+        if self.stream {
+            Value::Null
+        } else {
+            Value::String(Default::default())
+        }
+    }
+}
+
+/// Registers the LLM nodes that can be created by name.
+///
+/// `lemon_llm.llm` and `lemon_llm.llm_stream` nodes are created with an Ollama backend.
+/// The config is a [Value::Vec] of `["ollama", model, url]`.
+#[cfg(feature = "ollama")]
+pub fn register(registry: &mut NodeRegistry) {
+    use ollama::OllamaBackend;
+
+    for (name, stream) in [
+        (LlmWeight::<OllamaBackend>::NAME, false),
+        (LlmWeight::<OllamaBackend>::STREAM_NAME, true),
+    ] {
+        let (kind, response) = if stream {
+            (ValueKind::Stream, Value::Null)
+        } else {
+            (ValueKind::String, Value::String(Default::default()))
+        };
+
+        registry.register(NodeType::new(
+            name,
+            vec![Port::new(
+                "prompt",
+                ValueKind::String,
+                Value::String(Default::default()),
+            )],
+            vec![Port::new("response", kind, response)],
+            move |config| {
+                let backend = match config {
+                    Some(config) => OllamaBackend::from_config(config)
+                        .ok_or_else(|| LoadError::InvalidConfig(name.to_string()))?,
+                    None => OllamaBackend::default(),
+                };
+
+                Ok(GraphNode::AsyncNode(Arc::new(LlmWeight {
+                    backend: Arc::new(backend),
+                    stream,
+                })))
+            },
+        ));
     }
 }
 
+#[async_trait]
 impl<T: LlmBackend> AsyncNode for LlmWeight<T> {
-    fn run(
+    async fn run(&self, inputs: Vec<Value>) -> Result<Vec<Value>, NodeError> {
+        self.run_with_context(&ExecutionContext::default(), inputs)
+            .await
+    }
+
+    async fn run_with_context(
         &self,
+        ctx: &ExecutionContext,
         inputs: Vec<Value>,
-    ) -> Box<dyn Future<Output = Result<Vec<Value>, NodeError>> + Unpin> {
-        let backend = self.backend.clone();
+    ) -> Result<Vec<Value>, NodeError> {
+        let prompt = match inputs.first() {
+            Some(Value::String(prompt)) => prompt,
+            Some(v) => return Err(NodeError::ConversionError(v.clone())),
+            None => return Err(NodeError::MissingInput(0)),
+        };
+
+        if self.stream {
+            let (sender, stream) = ValueStream::channel();
+            let backend = self.backend.clone();
+            let prompt = prompt.clone();
+            let cancel = ctx.cancel.clone();
+
+            // Generate in the background, so downstream nodes can read chunks as they arrive.
+            // Errors are passed to readers through the stream.
+            tokio::spawn(async move {
+                let res = tokio::select! {
+                    res = backend.generate_stream(&prompt, &sender) => res,
+                    _ = cancel.cancelled() => {
+                        sender.fail("Generation was cancelled");
+                        return;
+                    }
+                };
+
+                match res {
+                    Ok(()) => sender.close(),
+                    Err(e) => sender.fail(format!("Failed to generate: {}", e)),
+                }
+            });
+
+            return Ok(vec![Value::Stream(stream)]);
+        }
+
+        let response = self
+            .backend
+            .generate(prompt)
+            .await
+            .map_err(|e| NodeError::InternalError(format!("Failed to generate: {}", e)))?;
+
+        Ok(vec![Value::String(response)])
+    }
+
+    fn data(&self) -> Option<NodeData> {
+        let name = if self.stream {
+            Self::STREAM_NAME
+        } else {
+            Self::NAME
+        };
+
+        self.backend.config().map(|config| NodeData {
+            name: name.to_string(),
+            config: Some(config),
+        })
+    }
+
+    fn ports(&self) -> NodePorts<'_> {
+        let response = if self.stream {
+            ValueKind::Stream
+        } else {
+            ValueKind::String
+        };
+
+        NodePorts {
+            inputs: vec![NodePort::new("prompt", ValueKind::String)],
+            outputs: vec![NodePort::new("response", response)],
+        }
+    }
+}
 
-        Box::new(Box::pin(async move {
-            let prompt = match inputs.first() {
-                Some(Value::String(prompt)) => prompt.clone(),
+#[cfg(test)]
+mod tests {
+    use lemon_graph::Executor;
+
+    use super::*;
+
+    struct Echo;
+
+    impl LlmBackend for Echo {
+        async fn generate(&self, prompt: &str) -> Result<String, GenerateError> {
+            Ok(prompt.to_string())
+        }
+    }
+
+    /// Sends the prompt as a chunk, then fails or waits forever.
+    struct Broken {
+        hang: bool,
+    }
+
+    impl LlmBackend for Broken {
+        async fn generate(&self, _prompt: &str) -> Result<String, GenerateError> {
+            Err(GenerateError::BackendError("Broken".to_string()))
+        }
+
+        async fn generate_stream(
+            &self,
+            prompt: &str,
+            sender: &StreamSender,
+        ) -> Result<(), GenerateError> {
+            sender.send(Value::String(prompt.to_string()));
+
+            if self.hang {
+                std::future::pending::<()>().await;
+            }
+
+            Err(GenerateError::BackendError("Connection lost".to_string()))
+        }
+    }
+
+    /// Reads a stream, outputting its text.
+    struct Collect;
+
+    #[async_trait]
+    impl AsyncNode for Collect {
+        async fn run(&self, inputs: Vec<Value>) -> Result<Vec<Value>, NodeError> {
+            let stream = match inputs.first() {
+                Some(Value::Stream(stream)) => stream,
                 Some(v) => return Err(NodeError::ConversionError(v.clone())),
                 None => return Err(NodeError::MissingInput(0)),
             };
 
-            let response = backend
-                .generate(&prompt)
+            let text = stream
+                .collect_string()
                 .await
-                .map_err(|e| NodeError::InternalError(format!("Failed to generate: {}", e)))?;
+                .map_err(|e| NodeError::InternalError(e.to_string()))?;
+
+            Ok(vec![Value::String(text)])
+        }
+    }
+
+    /// Creates a streaming LLM node, followed by a node reading its stream.
+    /// Returns the LLM node, and the store holding the text that was read.
+    fn streaming_graph<T: LlmBackend + 'static>(
+        graph: &mut Graph,
+        backend: T,
+    ) -> (LlmNode, NodeIndex) {
+        let llm = LlmNode::streaming(graph, Arc::new(backend));
+        let input = llm.input(graph).unwrap();
+        input.set_value(graph, Value::String("Hello".to_string()));
+
+        let collect = graph.add_node(GraphNode::AsyncNode(Arc::new(Collect)));
+        graph.add_edge(llm.0, collect, GraphEdge::ExecutionFlow(0));
+        let output = llm.output(graph).unwrap();
+        graph.add_edge(output.0, collect, GraphEdge::DataMap(0));
+
+        let text = graph.add_node(GraphNode::Store(Value::Null));
+        graph.add_edge(collect, text, GraphEdge::DataMap(0));
+
+        (llm, text)
+    }
+
+    #[tokio::test]
+    async fn test_streaming() {
+        let mut graph = Graph::default();
+        let (llm, text) = streaming_graph(&mut graph, Echo);
+
+        Executor::execute(&mut graph, llm.0).await.unwrap();
+
+        assert!(matches!(&graph[text], GraphNode::Store(Value::String(s)) if s == "Hello"));
+    }
+
+    #[tokio::test]
+    async fn test_streaming_error() {
+        let mut graph = Graph::default();
+        let (llm, text) = streaming_graph(&mut graph, Broken { hang: false });
+
+        // The node reading the stream fails with the backend error.
+        let err = Executor::execute(&mut graph, llm.0).await.unwrap_err();
+        assert!(err.to_string().contains("Connection lost"));
+        assert!(matches!(graph[text], GraphNode::Store(Value::Null)));
+    }
+
+    #[cfg(feature = "ollama")]
+    #[test]
+    fn test_save_streaming() {
+        use lemon_graph::GraphData;
+        use ollama::OllamaBackend;
+
+        let mut graph = Graph::default();
+        let llm = LlmNode::streaming(&mut graph, Arc::new(OllamaBackend::default()));
+
+        let data = GraphData::save(&graph).unwrap();
+        let json = serde_json::to_string(&data).unwrap();
+        let data = serde_json::from_str::<GraphData>(&json).unwrap();
+
+        let mut registry = NodeRegistry::new();
+        register(&mut registry);
+        let loaded = data.clone().load(|data| registry.load(data)).unwrap();
+
+        assert_eq!(GraphData::save(&loaded).unwrap(), data);
+        let output = llm.output(&loaded).unwrap();
+        assert!(matches!(loaded[output.0], GraphNode::Store(Value::Null)));
+    }
+
+    #[tokio::test]
+    async fn test_streaming_cancel() {
+        let mut graph = Graph::default();
+        let llm = LlmNode::streaming(&mut graph, Arc::new(Broken { hang: true }));
+        let input = llm.input(&graph).unwrap();
+        input.set_value(&mut graph, Value::String("Hello".to_string()));
+
+        let executor = Executor::default();
+        let ctx = executor.context();
+        executor
+            .run_with_context(&mut graph, llm.0, ctx.clone())
+            .await
+            .unwrap();
+
+        let output = llm.output(&graph).unwrap();
+        let stream = match &graph[output.0] {
+            GraphNode::Store(Value::Stream(stream)) => stream.clone(),
+            _ => panic!("Expected a stream"),
+        };
 
-            Ok(vec![Value::String(response)])
-        }))
+        // Cancelling the execution stops generation, failing the stream.
+        ctx.cancel.cancel();
+        let err = stream.collect().await.unwrap_err();
+        assert!(err.to_string().contains("cancelled"));
     }
 }
@@ -1,4 +1,7 @@
+use std::str::FromStr;
+
 use futures_util::StreamExt;
+use lemon_graph::{StreamSender, Value};
 use serde::{Deserialize, Serialize};
 use tracing::{debug, info};
 
@@ -33,9 +36,74 @@ pub enum OllamaModel {
     Mixtral,
 }
 
+impl OllamaBackend {
+    pub const CONFIG_NAME: &'static str = "ollama";
+
+    /// Creates a backend from a config returned by [LlmBackend::config].
+    pub fn from_config(config: &Value) -> Option<Self> {
+        match config {
+            Value::Vec(values) => match values.as_slice() {
+                [Value::String(name), Value::String(model), Value::String(url)]
+                    if name == Self::CONFIG_NAME =>
+                {
+                    Some(Self {
+                        model: model.parse().ok()?,
+                        url: url.clone(),
+                    })
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+impl OllamaModel {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Llama2 => "llama2",
+            Self::Llama2Uncensored => "llama2-uncensored",
+            Self::Mistral => "mistral",
+            Self::Mixtral => "mixtral",
+        }
+    }
+}
+
+impl FromStr for OllamaModel {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "llama2" => Ok(Self::Llama2),
+            "llama2-uncensored" => Ok(Self::Llama2Uncensored),
+            "mistral" => Ok(Self::Mistral),
+            "mixtral" => Ok(Self::Mixtral),
+            _ => Err(()),
+        }
+    }
+}
+
 impl LlmBackend for OllamaBackend {
     async fn generate(&self, prompt: &str) -> Result<String, GenerateError> {
-        generate_ollama(&self.url, self.model, prompt).await
+        generate_ollama(&self.url, self.model, prompt, &|_| {}).await
+    }
+
+    async fn generate_stream(
+        &self,
+        prompt: &str,
+        sender: &StreamSender,
+    ) -> Result<(), GenerateError> {
+        let on_chunk = |chunk: &str| sender.send(Value::String(chunk.to_string()));
+        generate_ollama(&self.url, self.model, prompt, &on_chunk).await?;
+        Ok(())
+    }
+
+    fn config(&self) -> Option<Value> {
+        Some(Value::Vec(vec![
+            Value::String(Self::CONFIG_NAME.to_string()),
+            Value::String(self.model.as_str().to_string()),
+            Value::String(self.url.clone()),
+        ]))
     }
 }
 
@@ -44,6 +112,7 @@ async fn generate_ollama(
     url: &str,
     model: OllamaModel,
     prompt: &str,
+    on_chunk: &(dyn Fn(&str) + Send + Sync),
 ) -> Result<String, GenerateError> {
     let client = reqwest::Client::new();
 
@@ -86,7 +155,7 @@ async fn generate_ollama(
 
                         if let Ok(status) = serde_json::from_str::<OllamaStatus>(&text) {
                             if status.status == "success" {
-                                return generate_ollama(url, model, prompt).await;
+                                return generate_ollama(url, model, prompt, on_chunk).await;
                             }
 
                             if status.status == last_status {
@@ -103,6 +172,7 @@ async fn generate_ollama(
         }
 
         if let Ok(response) = serde_json::from_str::<OllamaResponse>(&text_chunk) {
+            on_chunk(&response.response);
             text.push_str(&response.response);
         }
     }
@@ -146,6 +216,20 @@ mod tests {
 
     const TEST_PROMPT: &str = "What letter comes after A?";
 
+    #[test]
+    fn test_config() {
+        let backend = OllamaBackend {
+            model: OllamaModel::Mixtral,
+            url: "http://example.com".to_string(),
+        };
+
+        let config = backend.config().unwrap();
+        let loaded = OllamaBackend::from_config(&config).unwrap();
+
+        assert_eq!(loaded.model, backend.model);
+        assert_eq!(loaded.url, backend.url);
+    }
+
     #[tokio::test]
     #[traced_test]
     async fn test_ollama_backend() {